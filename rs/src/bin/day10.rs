@@ -0,0 +1,365 @@
+#[path = "../grid.rs"]
+mod grid;
+
+use clap::Parser;
+use grid::{Direction, Grid, GridIndex, DIRECTIONS};
+use std::fmt;
+
+#[derive(Parser)]
+struct Cli {
+    path: std::path::PathBuf,
+
+    /// Print the maze with box-drawing pipes, coloured or plain
+    #[arg(long)]
+    render: Option<RenderMode>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, clap::ValueEnum)]
+enum RenderMode {
+    Ansi,
+    Plain,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+enum Tile {
+    #[default]
+    Ground,
+    Start,
+    Pipe(char),
+}
+
+// the pipe that joins two directions, in the puzzle's notation
+fn pipe_between(a: Direction, b: Direction) -> char {
+    let mut pair = [a, b];
+    pair.sort_by_key(|direction| DIRECTIONS.iter().position(|d| d == direction));
+    match pair {
+        [Direction::Up, Direction::Down] => return '|',
+        [Direction::Left, Direction::Right] => return '-',
+        [Direction::Up, Direction::Right] => return 'L',
+        [Direction::Up, Direction::Left] => return 'J',
+        [Direction::Down, Direction::Left] => return '7',
+        [Direction::Down, Direction::Right] => return 'F',
+        _ => panic!("no pipe joins {:?} and {:?}", a, b),
+    }
+}
+
+impl Tile {
+    fn connections(&self) -> &'static [Direction] {
+        match self {
+            Tile::Pipe('|') => return &[Direction::Up, Direction::Down],
+            Tile::Pipe('-') => return &[Direction::Left, Direction::Right],
+            Tile::Pipe('L') => return &[Direction::Up, Direction::Right],
+            Tile::Pipe('J') => return &[Direction::Up, Direction::Left],
+            Tile::Pipe('7') => return &[Direction::Down, Direction::Left],
+            Tile::Pipe('F') => return &[Direction::Down, Direction::Right],
+            _ => return &[],
+        }
+    }
+
+    fn connects(&self, direction: Direction) -> bool {
+        return self.connections().contains(&direction);
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum LoopError {
+    NoStart,
+    MultipleStarts(Vec<GridIndex>),
+    // no pair of the start's neighbours leads back to it
+    NoLoop(GridIndex),
+}
+
+impl fmt::Display for LoopError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LoopError::NoStart => write!(f, "the maze has no S"),
+            LoopError::MultipleStarts(starts) => {
+                write!(f, "the maze has {} S tiles at {}", starts.len(), starts.iter().map(|start| start.to_string()).collect::<Vec<String>>().join(", "))
+            },
+            LoopError::NoLoop(start) => write!(f, "no loop runs through S at {}", start),
+        }
+    }
+}
+
+// the main loop in walking order, starting at S
+#[derive(Debug, Clone, PartialEq)]
+struct Loop {
+    cells: Vec<GridIndex>,
+    // the pipe hidden under S
+    start_pipe: char,
+}
+
+impl Loop {
+    fn farthest(&self) -> usize {
+        return self.cells.len() / 2;
+    }
+
+    // twice the area enclosed by the centres of the loop's cells
+    fn double_area(&self) -> usize {
+        let n = self.cells.len();
+        let sum: i64 = (0..n).map(|k| {
+            let (a, b) = (self.cells[k], self.cells[(k + 1) % n]);
+            return a.j as i64 * b.i as i64 - b.j as i64 * a.i as i64;
+        }).sum();
+        return sum.unsigned_abs() as usize;
+    }
+
+    // Pick's theorem: area = interior + boundary / 2 - 1
+    fn interior(&self) -> usize {
+        return (self.double_area() + 2 - self.cells.len()) / 2;
+    }
+}
+
+fn parse_maze(content: &str) -> Grid<Tile> {
+    let lines = content.lines().collect::<Vec<&str>>();
+    let cols = lines.iter().map(|line| line.chars().count()).max().unwrap_or(0);
+    let mut grid: Grid<Tile> = Grid::new(lines.len(), cols);
+
+    for (i, line) in lines.iter().enumerate() {
+        for (j, c) in line.chars().enumerate() {
+            let index = grid.at(i, j);
+            *grid.get_mut(index).unwrap() = match c {
+                '.' => Tile::Ground,
+                'S' => Tile::Start,
+                '|' | '-' | 'L' | 'J' | '7' | 'F' => Tile::Pipe(c),
+                c => panic!("invalid tile {:?} at line {}, column {}", c, i + 1, j + 1),
+            };
+        }
+    }
+    return grid;
+}
+
+// follow the pipes leaving `start` towards `direction`, returns the cells and the direction
+// the walk came back in, or None if the pipes break off first
+fn walk(grid: &Grid<Tile>, start: GridIndex, mut direction: Direction) -> Option<(Vec<GridIndex>, Direction)> {
+    let mut cells = vec![start];
+    let mut current = start;
+    loop {
+        current = current.step(direction)?;
+        let tile = grid.get(current)?;
+        if *tile == Tile::Start {
+            return Some((cells, direction));
+        }
+        if !tile.connects(direction.opposite()) {
+            return None;
+        }
+        cells.push(current);
+        direction = *tile.connections().iter().find(|d| **d != direction.opposite()).unwrap();
+    }
+}
+
+fn find_loop(grid: &Grid<Tile>) -> Result<Loop, LoopError> {
+    let starts = (0..grid.rows)
+        .flat_map(|i| (0..grid.cols).map(move |j| (i, j)))
+        .map(|(i, j)| grid.at(i, j))
+        .filter(|index| grid.get(*index) == Some(&Tile::Start))
+        .collect::<Vec<GridIndex>>();
+    let start = match starts.as_slice() {
+        [] => return Err(LoopError::NoStart),
+        [start] => *start,
+        _ => return Err(LoopError::MultipleStarts(starts)),
+    };
+
+    // S is whatever pipe joins the way out to the way back in
+    for direction in DIRECTIONS {
+        if let Some((cells, back)) = walk(grid, start, direction) {
+            return Ok(Loop { cells, start_pipe: pipe_between(direction, back.opposite()) });
+        }
+    }
+    return Err(LoopError::NoLoop(start));
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+enum Region {
+    #[default]
+    Outside,
+    Inside,
+    Loop,
+}
+
+// scan every row, a tile is inside after crossing the loop an odd number of times;
+// only pipes that reach up count as crossings, so runs along the loop are handled
+fn regions(grid: &Grid<Tile>, main_loop: &Loop) -> Grid<Region> {
+    let mut regions: Grid<Region> = Grid::new(grid.rows, grid.cols);
+    for cell in main_loop.cells.iter() {
+        *regions.get_mut(*cell).unwrap() = Region::Loop;
+    }
+
+    for i in 0..grid.rows {
+        let mut inside = false;
+        for j in 0..grid.cols {
+            let index = grid.at(i, j);
+            if regions.get(index) == Some(&Region::Loop) {
+                let tile = match grid.get(index) {
+                    Some(Tile::Start) => Tile::Pipe(main_loop.start_pipe),
+                    tile => *tile.unwrap(),
+                };
+                if tile.connects(Direction::Up) {
+                    inside = !inside;
+                }
+            } else if inside {
+                *regions.get_mut(index).unwrap() = Region::Inside;
+            }
+        }
+    }
+    return regions;
+}
+
+fn box_drawing(pipe: char, heavy: bool) -> char {
+    match (pipe, heavy) {
+        ('|', false) => return '│',
+        ('-', false) => return '─',
+        ('L', false) => return '└',
+        ('J', false) => return '┘',
+        ('7', false) => return '┐',
+        ('F', false) => return '┌',
+        ('|', true) => return '┃',
+        ('-', true) => return '━',
+        ('L', true) => return '┗',
+        ('J', true) => return '┛',
+        ('7', true) => return '┓',
+        ('F', true) => return '┏',
+        _ => return '·',
+    }
+}
+
+const LOOP_COLOUR: &str = "\x1b[1;33m";
+const INSIDE_COLOUR: &str = "\x1b[32m";
+const OUTSIDE_COLOUR: &str = "\x1b[2;34m";
+const RESET: &str = "\x1b[0m";
+
+// the loop in heavy lines; plain text marks the other tiles I or O like the puzzle does,
+// ANSI keeps their pipes and colours them by region instead
+fn render(grid: &Grid<Tile>, main_loop: &Loop, mode: RenderMode) -> String {
+    let regions = regions(grid, main_loop);
+    let mut s = String::new();
+    for i in 0..grid.rows {
+        for j in 0..grid.cols {
+            let index = grid.at(i, j);
+            let tile = *grid.get(index).unwrap();
+            let region = *regions.get(index).unwrap();
+            let pipe = match tile {
+                Tile::Start => main_loop.start_pipe,
+                Tile::Pipe(pipe) => pipe,
+                Tile::Ground => '.',
+            };
+
+            match (mode, region) {
+                (RenderMode::Plain, Region::Loop) => s.push(box_drawing(pipe, true)),
+                (RenderMode::Plain, Region::Inside) => s.push('I'),
+                (RenderMode::Plain, Region::Outside) => s.push('O'),
+                (RenderMode::Ansi, Region::Loop) => s.push_str(&format!("{}{}{}", LOOP_COLOUR, box_drawing(pipe, true), RESET)),
+                (RenderMode::Ansi, Region::Inside) => s.push_str(&format!("{}{}{}", INSIDE_COLOUR, box_drawing(pipe, false), RESET)),
+                (RenderMode::Ansi, Region::Outside) => s.push_str(&format!("{}{}{}", OUTSIDE_COLOUR, box_drawing(pipe, false), RESET)),
+            }
+        }
+        s.push('\n');
+    }
+    return s;
+}
+
+fn solve_part1(content: String) -> usize {
+    let grid = parse_maze(&content);
+    let main_loop = find_loop(&grid).unwrap_or_else(|error| panic!("{}", error));
+    return main_loop.farthest();
+}
+
+fn solve_part2(content: String) -> usize {
+    let grid = parse_maze(&content);
+    let main_loop = find_loop(&grid).unwrap_or_else(|error| panic!("{}", error));
+    return main_loop.interior();
+}
+
+fn main() {
+    let args = Cli::parse();
+    println!("input: {:?}", args.path);
+
+    let content = std::fs::read_to_string(&args.path).expect("could not read file");
+
+    if let Some(mode) = args.render {
+        let grid = parse_maze(&content);
+        match find_loop(&grid) {
+            Ok(main_loop) => print!("{}", render(&grid, &main_loop, mode)),
+            Err(error) => eprintln!("cannot render: {}", error),
+        }
+    }
+
+    println!("part1: {}", solve_part1(content.clone()));
+    println!("part2: {}", solve_part2(content.clone()));
+}
+
+#[cfg(test)]
+mod loop_tests {
+    use crate::*;
+
+    pub const SQUARE: &str = "-L|F7\n7S-7|\nL|7||\n-L-J|\nL|-JF";
+    const FARTHEST: &str = "..F7.\n.FJ|.\nSJ.L7\n|F--J\nLJ...";
+    pub const SQUEEZED: &str = "..........\n.S------7.\n.|F----7|.\n.||....||.\n.||....||.\n.|L-7F-J|.\n.|..||..|.\n.L--JL--J.\n..........";
+    const LARGER: &str = ".F----7F7F7F7F-7....\n.|F--7||||||||FJ....\n.||.FJ||||||||L7....\nFJL7L7LJLJ||LJ.L-7..\nL--J.L7...LJS7F-7L7.\n....F-J..F7FJ|L7L7L7\n....L7.F7||L7|.L7L7|\n.....|FJLJ|FJ|F7|.LJ\n....FJL-7.||.||||...\n....L---J.LJ.LJLJ...";
+
+    #[test]
+    fn infers_the_pipe_under_start() {
+        assert_eq!(find_loop(&parse_maze(SQUARE)).unwrap().start_pipe, 'F');
+        assert_eq!(find_loop(&parse_maze(FARTHEST)).unwrap().start_pipe, 'F');
+        assert_eq!(find_loop(&parse_maze(LARGER)).unwrap().start_pipe, 'F');
+    }
+
+    #[test]
+    fn walks_the_loop_in_order() {
+        let grid = parse_maze(SQUARE);
+        let cells = find_loop(&grid).unwrap().cells.iter().map(|cell| (cell.i, cell.j)).collect::<Vec<(usize, usize)>>();
+
+        assert_eq!(cells, vec![(1, 1), (2, 1), (3, 1), (3, 2), (3, 3), (2, 3), (1, 3), (1, 2)]);
+    }
+
+    #[test]
+    fn solves_examples() {
+        assert_eq!(solve_part1(SQUARE.to_string()), 4);
+        assert_eq!(solve_part1(FARTHEST.to_string()), 8);
+        assert_eq!(solve_part2(SQUARE.to_string()), 1);
+        assert_eq!(solve_part2(SQUEEZED.to_string()), 4);
+        assert_eq!(solve_part2(LARGER.to_string()), 8);
+    }
+
+    #[test]
+    fn reports_broken_mazes() {
+        assert_eq!(find_loop(&parse_maze("...\n.F.\n...")), Err(LoopError::NoStart));
+        let grid = parse_maze("...\n.S-\n...");
+        assert_eq!(find_loop(&grid), Err(LoopError::NoLoop(grid.at(1, 1))));
+    }
+}
+
+#[cfg(test)]
+mod render_tests {
+    use crate::*;
+    use crate::loop_tests::{SQUARE, SQUEEZED};
+
+    #[test]
+    fn plain_render_marks_regions() {
+        let grid = parse_maze(SQUARE);
+        let main_loop = find_loop(&grid).unwrap();
+
+        assert_eq!(render(&grid, &main_loop, RenderMode::Plain), "OOOOO\nO┏━┓O\nO┃I┃O\nO┗━┛O\nOOOOO\n");
+    }
+
+    #[test]
+    fn scanline_regions_agree_with_pick() {
+        let grid = parse_maze(SQUEEZED);
+        let main_loop = find_loop(&grid).unwrap();
+        let inside = regions(&grid, &main_loop).data.iter().filter(|region| **region == Region::Inside).count();
+
+        assert_eq!(inside, main_loop.interior());
+        assert_eq!(render(&grid, &main_loop, RenderMode::Plain).matches('I').count(), 4);
+    }
+
+    #[test]
+    fn ansi_render_colours_every_tile() {
+        let grid = parse_maze(SQUEEZED);
+        let main_loop = find_loop(&grid).unwrap();
+        let rendered = render(&grid, &main_loop, RenderMode::Ansi);
+
+        assert_eq!(rendered.matches(RESET).count(), grid.rows * grid.cols);
+        assert_eq!(rendered.matches(INSIDE_COLOUR).count(), 4);
+        assert!(rendered.contains(&format!("{}┏{}", LOOP_COLOUR, RESET)));
+    }
+}
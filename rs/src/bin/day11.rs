@@ -0,0 +1,127 @@
+use clap::Parser;
+
+#[derive(Parser)]
+struct Cli {
+    path: std::path::PathBuf,
+
+    /// Also sum the distances for these expansion factors
+    #[arg(long, value_delimiter = ',')]
+    factor: Vec<u64>,
+}
+
+// (row, column) of every galaxy
+fn parse_galaxies(content: &str) -> Vec<(usize, usize)> {
+    return content
+        .lines()
+        .enumerate()
+        .flat_map(|(i, line)| line.chars().enumerate().filter(|(_, c)| *c == '#').map(move |(j, _)| (i, j)))
+        .collect();
+}
+
+// positions along one axis after every empty line grows to `factor` lines, in input order
+fn expand(coordinates: &[usize], factor: u64) -> Vec<u128> {
+    let mut sorted = coordinates.to_vec();
+    sorted.sort();
+    sorted.dedup();
+
+    // sorted[k] has k occupied lines before it, the remaining sorted[k] - k are empty
+    return coordinates.iter().map(|coordinate| {
+        let occupied = sorted.partition_point(|c| c < coordinate);
+        let empty = (coordinate - occupied) as u128;
+        return occupied as u128 + empty * factor as u128;
+    }).collect();
+}
+
+// sum of |a - b| over all pairs: after sorting, each value is subtracted from by
+// everything after it and subtracts everything before it
+fn pairwise_distance_sum(mut values: Vec<u128>) -> u128 {
+    values.sort();
+    let mut prefix: u128 = 0;
+    let mut total: u128 = 0;
+    for (k, value) in values.iter().enumerate() {
+        total += value * k as u128 - prefix;
+        prefix += value;
+    }
+    return total;
+}
+
+fn total_distance(content: &str, factor: u64) -> u128 {
+    let galaxies = parse_galaxies(content);
+    let rows = galaxies.iter().map(|(i, _)| *i).collect::<Vec<usize>>();
+    let cols = galaxies.iter().map(|(_, j)| *j).collect::<Vec<usize>>();
+    return pairwise_distance_sum(expand(&rows, factor)) + pairwise_distance_sum(expand(&cols, factor));
+}
+
+fn solve_part1(content: String) -> u128 {
+    return total_distance(&content, 2);
+}
+
+fn solve_part2(content: String) -> u128 {
+    return total_distance(&content, 1_000_000);
+}
+
+fn main() {
+    let args = Cli::parse();
+    println!("input: {:?}", args.path);
+
+    let content = std::fs::read_to_string(&args.path).expect("could not read file");
+
+    for factor in args.factor.iter() {
+        println!("factor {}: {}", factor, total_distance(&content, *factor));
+    }
+
+    println!("part1: {}", solve_part1(content.clone()));
+    println!("part2: {}", solve_part2(content.clone()));
+}
+
+#[cfg(test)]
+mod expansion_tests {
+    use crate::*;
+
+    const EXAMPLE: &str = "...#......\n.......#..\n#.........\n..........\n......#...\n.#........\n.........#\n..........\n.......#..\n#...#.....";
+
+    // every pair, the way the puzzle describes it
+    fn brute_force(content: &str, factor: u64) -> u128 {
+        let galaxies = parse_galaxies(content);
+        let rows = expand(&galaxies.iter().map(|(i, _)| *i).collect::<Vec<usize>>(), factor);
+        let cols = expand(&galaxies.iter().map(|(_, j)| *j).collect::<Vec<usize>>(), factor);
+
+        let mut total = 0;
+        for a in 0..galaxies.len() {
+            for b in a + 1..galaxies.len() {
+                total += rows[a].abs_diff(rows[b]) + cols[a].abs_diff(cols[b]);
+            }
+        }
+        return total;
+    }
+
+    #[test]
+    fn solves_example() {
+        assert_eq!(total_distance(EXAMPLE, 2), 374);
+        assert_eq!(total_distance(EXAMPLE, 10), 1030);
+        assert_eq!(total_distance(EXAMPLE, 100), 8410);
+    }
+
+    #[test]
+    fn expands_empty_lines() {
+        // lines 1, 2 and 4 are empty
+        assert_eq!(expand(&[3, 0, 5, 3], 10), vec![21, 0, 32, 21]);
+        // a factor of one leaves everything in place, zero removes empty lines
+        assert_eq!(expand(&[3, 0, 5], 1), vec![3, 0, 5]);
+        assert_eq!(expand(&[3, 0, 5], 0), vec![1, 0, 2]);
+    }
+
+    #[test]
+    fn prefix_sums_match_every_pair() {
+        for factor in [0, 1, 2, 7, 1_000_000] {
+            assert_eq!(total_distance(EXAMPLE, factor), brute_force(EXAMPLE, factor));
+        }
+    }
+
+    #[test]
+    fn huge_factors_do_not_overflow() {
+        let expected = brute_force(EXAMPLE, u64::MAX);
+        assert_eq!(total_distance(EXAMPLE, u64::MAX), expected);
+        assert!(expected > u64::MAX as u128);
+    }
+}
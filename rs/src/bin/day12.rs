@@ -0,0 +1,204 @@
+use clap::Parser;
+
+#[derive(Parser)]
+struct Cli {
+    path: std::path::PathBuf,
+
+    /// Also count arrangements with the rows unfolded this many times
+    #[arg(long, value_delimiter = ',')]
+    unfold: Vec<usize>,
+
+    /// List every arrangement of rows that have at most this many
+    #[arg(long)]
+    list: Option<u128>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Row {
+    springs: Vec<char>,
+    groups: Vec<usize>,
+}
+
+impl Row {
+    fn parse(line: &str) -> Self {
+        let (springs, groups) = line.split_once(' ').unwrap_or_else(|| panic!("expected springs and groups in {:?}", line));
+        let springs = springs.chars().map(|c| match c {
+            '.' | '#' | '?' => c,
+            c => panic!("invalid spring {:?} in {:?}", c, line),
+        }).collect();
+        let groups = groups.split(',').map(|s| s.parse::<usize>().expect("invalid group size")).collect();
+        return Row { springs, groups };
+    }
+
+    // `factor` copies of the springs joined by '?', and the groups repeated as often
+    fn unfold(&self, factor: usize) -> Row {
+        let copies = vec![self.springs.clone(); factor];
+        return Row { springs: copies.join(&'?'), groups: self.groups.repeat(factor) };
+    }
+
+    // ways[position][group] counts the arrangements of springs[position..] into groups[group..]
+    fn table(&self) -> Vec<Vec<u128>> {
+        let (n, m) = (self.springs.len(), self.groups.len());
+
+        // operational[k] is the number of '.' in springs[..k]
+        let mut operational = vec![0; n + 1];
+        for (k, spring) in self.springs.iter().enumerate() {
+            operational[k + 1] = operational[k] + (*spring == '.') as usize;
+        }
+
+        let mut ways = vec![vec![0u128; m + 1]; n + 1];
+        ways[n][m] = 1;
+        for position in (0..n).rev() {
+            for group in 0..=m {
+                let spring = self.springs[position];
+                let mut count = 0;
+                if spring != '#' {
+                    count += ways[position + 1][group];
+                }
+                if spring != '.' && group < m {
+                    // the group covers springs[position..end] and needs a gap after it
+                    let end = position + self.groups[group];
+                    let fits = end <= n && operational[end] == operational[position] && (end == n || self.springs[end] != '#');
+                    if fits {
+                        count += ways[(end + 1).min(n)][group + 1];
+                    }
+                }
+                ways[position][group] = count;
+            }
+        }
+        return ways;
+    }
+
+    fn count(&self) -> u128 {
+        return self.table()[0][0];
+    }
+
+    // every arrangement with the '?' filled in, only following choices that still have
+    // arrangements left; None when there are more than `limit`
+    fn arrangements(&self, limit: u128) -> Option<Vec<String>> {
+        let ways = self.table();
+        if ways[0][0] > limit {
+            return None;
+        }
+
+        let mut result = vec![];
+        let mut current = vec!['.'; self.springs.len()];
+        self.fill(&ways, 0, 0, &mut current, &mut result);
+        return Some(result);
+    }
+
+    fn fill(&self, ways: &[Vec<u128>], position: usize, group: usize, current: &mut Vec<char>, result: &mut Vec<String>) {
+        let n = self.springs.len();
+        if position == n {
+            result.push(current.iter().collect());
+            return;
+        }
+
+        if self.springs[position] != '#' && ways[position + 1][group] > 0 {
+            current[position] = '.';
+            self.fill(ways, position + 1, group, current, result);
+        }
+
+        if self.springs[position] != '.' && group < self.groups.len() {
+            let end = position + self.groups[group];
+            let fits = end <= n
+                && self.springs[position..end].iter().all(|spring| *spring != '.')
+                && (end == n || self.springs[end] != '#');
+            if fits && ways[(end + 1).min(n)][group + 1] > 0 {
+                current[position..end].fill('#');
+                if end < n {
+                    current[end] = '.';
+                }
+                self.fill(ways, (end + 1).min(n), group + 1, current, result);
+                current[position..end].fill('.');
+            }
+        }
+    }
+}
+
+fn parse_rows(content: &str) -> Vec<Row> {
+    return content.lines().filter(|line| !line.trim().is_empty()).map(Row::parse).collect();
+}
+
+fn total_arrangements(content: &str, factor: usize) -> u128 {
+    return parse_rows(content).iter().map(|row| row.unfold(factor).count()).sum();
+}
+
+fn solve_part1(content: String) -> u128 {
+    return total_arrangements(&content, 1);
+}
+
+fn solve_part2(content: String) -> u128 {
+    return total_arrangements(&content, 5);
+}
+
+fn main() {
+    let args = Cli::parse();
+    println!("input: {:?}", args.path);
+
+    let content = std::fs::read_to_string(&args.path).expect("could not read file");
+
+    if let Some(limit) = args.list {
+        for row in parse_rows(&content) {
+            let springs = row.springs.iter().collect::<String>();
+            match row.arrangements(limit) {
+                Some(arrangements) => {
+                    println!("{}: {} arrangements", springs, arrangements.len());
+                    for arrangement in arrangements {
+                        println!("  {}", arrangement);
+                    }
+                },
+                None => println!("{}: {} arrangements, not listed", springs, row.count()),
+            }
+        }
+    }
+
+    for factor in args.unfold.iter() {
+        println!("unfold {}: {}", factor, total_arrangements(&content, *factor));
+    }
+
+    println!("part1: {}", solve_part1(content.clone()));
+    println!("part2: {}", solve_part2(content.clone()));
+}
+
+#[cfg(test)]
+mod arrangement_tests {
+    use crate::*;
+
+    const EXAMPLE: &str = "???.### 1,1,3\n.??..??...?##. 1,1,3\n?#?#?#?#?#?#?#? 1,3,1,6\n????.#...#... 4,1,1\n????.######..#####. 1,6,5\n?###???????? 3,2,1";
+
+    #[test]
+    fn solves_example() {
+        let counts = parse_rows(EXAMPLE).iter().map(|row| row.count()).collect::<Vec<u128>>();
+        assert_eq!(counts, vec![1, 4, 1, 1, 4, 10]);
+
+        let unfolded = parse_rows(EXAMPLE).iter().map(|row| row.unfold(5).count()).collect::<Vec<u128>>();
+        assert_eq!(unfolded, vec![1, 16384, 1, 16, 2500, 506250]);
+
+        assert_eq!(solve_part1(EXAMPLE.to_string()), 21);
+        assert_eq!(solve_part2(EXAMPLE.to_string()), 525152);
+    }
+
+    #[test]
+    fn unfolds_any_number_of_times() {
+        let row = Row::parse(".# 1");
+        assert_eq!(row.unfold(3), Row::parse(".#?.#?.# 1,1,1"));
+        assert_eq!(row.unfold(0).count(), 1);
+        assert_eq!(total_arrangements(EXAMPLE, 1), 21);
+
+        // every extra copy multiplies the possibilities, far past u64
+        assert!(Row::parse("???????? 1").unfold(30).count() > u64::MAX as u128);
+    }
+
+    #[test]
+    fn lists_arrangements() {
+        let row = Row::parse("?###???????? 3,2,1");
+        let arrangements = row.arrangements(100).unwrap();
+
+        assert_eq!(arrangements.len(), 10);
+        assert_eq!(arrangements[0], ".###....##.#");
+        assert_eq!(arrangements[9], ".###.##.#...");
+        assert!(arrangements.iter().all(|arrangement| Row::parse(&format!("{} 3,2,1", arrangement)).count() == 1));
+        assert_eq!(row.arrangements(9), None);
+    }
+}
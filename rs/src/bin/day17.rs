@@ -1,4 +1,11 @@
+#[path = "../grid.rs"]
+mod grid;
+
 use clap::Parser;
+use grid::{Direction, Grid, GridIndex};
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use std::collections::HashSet;
 use std::fmt;
 use std::hash::Hash;
 use priority_queue::PriorityQueue;
@@ -6,126 +13,52 @@ use priority_queue::PriorityQueue;
 #[derive(Parser)]
 struct Cli {
     path: std::path::PathBuf,
-}
 
-#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
-enum Direction {
-    Up,
-    Down,
-    Left,
-    Right,
-}
+    /// Also list the k best distinct routes
+    #[arg(long)]
+    routes: Option<usize>,
 
-impl Direction {
-    fn turn_left(&self) -> Self {
-        match self {
-            Direction::Up => Direction::Left,
-            Direction::Down => Direction::Right,
-            Direction::Left => Direction::Down,
-            Direction::Right => Direction::Up,
-        }
-    }
+    /// Write the grid and best route as a PPM heat map
+    #[arg(long)]
+    ppm: Option<std::path::PathBuf>,
 
-    fn turn_right(&self) -> Self {
-        match self {
-            Direction::Up => Direction::Right,
-            Direction::Down => Direction::Left,
-            Direction::Left => Direction::Up,
-            Direction::Right => Direction::Down,
-        }
-    }
+    /// Write the grid and best route as an SVG
+    #[arg(long)]
+    svg: Option<std::path::PathBuf>,
 
-    fn opposite(&self) -> Self {
-        match self {
-            Direction::Up => Direction::Down,
-            Direction::Down => Direction::Up,
-            Direction::Left => Direction::Right,
-            Direction::Right => Direction::Left,
-        }
-    }
+    /// Pixels per cell in the PPM
+    #[arg(long, default_value_t = 4)]
+    scale: usize,
 }
 
-
-#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq)]
-struct GridIndex {
-    i: usize,
-    j: usize,
-
-    rows: usize,
-    cols: usize,
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+enum Terrain {
+    #[default]
+    Wall,
+    Cost(usize),
 }
 
-impl GridIndex {
-    fn step(self, direction: Direction) -> Option<Self> {
-        let mut newIndex = self.clone();
-        match direction {
-            Direction::Up => if self.i > 0 {newIndex.i -= 1} else {return None},
-            Direction::Down => if self.i < self.rows - 1 {newIndex.i += 1} else {return None}
-            Direction::Left => if self.j > 0 {newIndex.j -= 1} else {return None},
-            Direction::Right => if self.j < self.cols - 1 {newIndex.j += 1} else {return None},
+impl Terrain {
+    fn cost(&self) -> Option<usize> {
+        match self {
+            Terrain::Wall => None,
+            Terrain::Cost(cost) => Some(*cost),
         }
-        return Some(newIndex);
     }
-
-    fn manhattan_distance(&self, other: &GridIndex) -> usize {
-        let dx = if self.i > other.i {self.i - other.i} else {other.i - self.i};
-        let dy = if self.j > other.j {self.j - other.j} else {other.j - self.j};
-        return dx + dy;
-    }
-}
-
-#[derive(Debug)]
-struct Grid<T> {
-    rows: usize,
-    cols: usize,
-    data: Vec<T>,
 }
 
-impl<T: Clone + Default> Grid<T> {
-    fn new(rows: usize, cols: usize) -> Self {
-        let data = vec![T::default(); rows * cols];
-        return Grid { rows, cols, data };
-    }
-
-    fn index(&self, index: GridIndex) -> usize {
-        index.i * self.rows + index.j
-    }   
-
-    fn size(&self) -> usize {
-        self.rows * self.cols
-    }
-
-    fn get(&self, index: GridIndex) -> Option<&T> {
-        if index.i >= self.rows || index.j >= self.cols {
-            return None;
-        }
-        self.data.get(self.index(index))
-    }
-
-    fn get_mut(&mut self, index: GridIndex) -> Option<&mut T> {
-        let index1d = self.index(index);
-        return self.data.get_mut(index1d);
-    }
-
-    fn set(&mut self, index: GridIndex, value: T) -> Option<()> {
-        if let Some(index1d) = self.get_mut(index) {
-            *index1d = value;
-            return Some(());
+impl fmt::Display for Terrain {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Terrain::Wall => write!(f, "#"),
+            Terrain::Cost(cost) => write!(f, "{}", cost),
         }
-        None
     }
 }
 
-impl<T: Clone + Default + std::fmt::Display> std::fmt::Display for Grid<T> {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        let mut s = String::new();
-        for i in 0..self.rows {
-            for j in 0..self.cols {
-                s.push_str(&format!("{} ", self.get(GridIndex { i, j, rows: self.rows, cols: self.cols }).unwrap()));
-            }
-            s.push_str("\n");
-        }
-        write!(f, "{}", s)
+impl Grid<Terrain> {
+    fn cost(&self, index: GridIndex) -> Option<usize> {
+        return self.get(index).and_then(|terrain| terrain.cost());
     }
 }
 
@@ -192,57 +125,364 @@ impl State {
     // }
 }
 
-fn search(grid: &Grid<usize>, start: State, goal: &State) -> Option<usize> {
-    let mut queue: PriorityQueue<(State, usize), i32> = PriorityQueue::new();
+#[derive(Debug, PartialEq, Eq)]
+enum SearchError {
+    StartBlocked(GridIndex),
+    GoalBlocked(GridIndex),
+    Unreachable { goal: GridIndex, states_visited: usize, cells_reached: usize },
+}
+
+impl fmt::Display for SearchError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SearchError::StartBlocked(index) => write!(f, "start ({}, {}) is a wall", index.i, index.j),
+            SearchError::GoalBlocked(index) => write!(f, "goal ({}, {}) is a wall", index.i, index.j),
+            SearchError::Unreachable { goal, states_visited, cells_reached } => write!(
+                f,
+                "goal ({}, {}) is unreachable: walls cut it off after visiting {} states on {} cells",
+                goal.i, goal.j, states_visited, cells_reached
+            ),
+        }
+    }
+}
+
+fn search(grid: &Grid<Terrain>, start: State, goal: &State) -> Result<usize, SearchError> {
+    if grid.cost(start.position).is_none() {
+        return Err(SearchError::StartBlocked(start.position));
+    }
+    if grid.cost(goal.position).is_none() {
+        return Err(SearchError::GoalBlocked(goal.position));
+    }
+
+    let mut queue: PriorityQueue<(State, usize), Reverse<usize>> = PriorityQueue::new();
 
     let mut nodes_visited : usize = 0;
-    queue.push((start, 0), 0);
+    let mut cells_reached = HashSet::<GridIndex>::new();
+    queue.push((start, 0), Reverse(0));
 
     while let Some(((state, cost), priority)) = queue.pop() {
         nodes_visited += 1;
+        cells_reached.insert(state.position);
         // println!("{:?}, cost: {}, priority: {}", state, cost, priority);
 
         // check goal
         if state.position.i == goal.position.i && state.position.j == goal.position.j {
             println!("Visited {} nodes", nodes_visited);
-            return Some(cost);
+            return Ok(cost);
         }
 
         for next_state in state.next_states() {
-            if let Some(value) = grid.get(next_state.position) {
-                let next_cost = cost + *value;
+            if let Some(value) = grid.cost(next_state.position) {
+                let next_cost = cost + value;
 
                 // A* using the manhattan distance as heuristic
-                let priority = - ((next_cost + next_state.position.manhattan_distance(&goal.position)) as i32);
+                let priority = Reverse(next_cost + next_state.position.manhattan_distance(&goal.position));
                 queue.push_increase((next_state, next_cost), priority);
             }
         }
     }
 
+    return Err(SearchError::Unreachable { goal: goal.position, states_visited: nodes_visited, cells_reached: cells_reached.len() });
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Route {
+    states: Vec<State>,
+    cost: usize,
+}
+
+impl Route {
+    fn directions(&self) -> String {
+        return self.states[1..].iter().map(|state| state.direction.arrow()).collect();
+    }
+}
+
+fn is_goal(state: &State, goal: &GridIndex) -> bool {
+    return state.position.i == goal.i && state.position.j == goal.j;
+}
+
+// A* like `search`, but keeps track of the route and skips blocked states and moves
+fn shortest_route(grid: &Grid<Terrain>, start: State, goal: &GridIndex, blocked_states: &HashSet<State>, blocked_moves: &HashSet<(State, State)>) -> Option<Route> {
+    let mut queue: PriorityQueue<State, Reverse<usize>> = PriorityQueue::new();
+    let mut costs: HashMap<State, usize> = HashMap::new();
+    let mut previous: HashMap<State, State> = HashMap::new();
+
+    costs.insert(start, 0);
+    queue.push(start, Reverse(0));
+
+    while let Some((state, _)) = queue.pop() {
+        let cost = costs[&state];
+
+        if is_goal(&state, goal) {
+            let mut states = vec![state];
+            while let Some(prev) = previous.get(states.last().unwrap()) {
+                states.push(*prev);
+            }
+            states.reverse();
+            return Some(Route { states, cost });
+        }
+
+        for next_state in state.next_states() {
+            if blocked_states.contains(&next_state) || blocked_moves.contains(&(state, next_state)) {
+                continue;
+            }
+
+            if let Some(value) = grid.cost(next_state.position) {
+                let next_cost = cost + value;
+
+                if costs.get(&next_state).is_none_or(|known| next_cost < *known) {
+                    costs.insert(next_state, next_cost);
+                    previous.insert(next_state, state);
+
+                    let priority = Reverse(next_cost + next_state.position.manhattan_distance(goal));
+                    queue.push_increase(next_state, priority);
+                }
+            }
+        }
+    }
+
     return None;
 }
 
-fn content_to_matrix(content: String) -> Grid<usize> {
-    let lines = content.lines().collect::<Vec<&str>>();
+// Yen's algorithm over the (position, direction, steps) state graph
+fn k_shortest_routes(grid: &Grid<Terrain>, start: State, goal: &GridIndex, k: usize) -> Vec<Route> {
+    let mut routes = Vec::<Route>::new();
+    let mut candidates = Vec::<Route>::new();
+
+    if k == 0 {
+        return routes;
+    }
+
+    match shortest_route(grid, start, goal, &HashSet::new(), &HashSet::new()) {
+        Some(route) => routes.push(route),
+        None => return routes,
+    }
+
+    while routes.len() < k {
+        let last = routes.last().unwrap().clone();
+
+        for i in 0..last.states.len() - 1 {
+            let root = &last.states[..=i];
+            let spur = last.states[i];
+
+            // don't take a move that one of the known routes with the same root already took
+            let blocked_moves: HashSet<(State, State)> = routes
+                .iter()
+                .filter(|route| route.states.len() > i + 1 && route.states[..=i] == *root)
+                .map(|route| (route.states[i], route.states[i + 1]))
+                .collect();
+            // and don't loop back into the root
+            let blocked_states: HashSet<State> = root[..i].iter().cloned().collect();
+
+            if let Some(spur_route) = shortest_route(grid, spur, goal, &blocked_states, &blocked_moves) {
+                let root_cost: usize = root[1..].iter().map(|state| grid.cost(state.position).unwrap()).sum();
+
+                let mut states = root[..i].to_vec();
+                states.extend(spur_route.states);
+                let route = Route { states, cost: root_cost + spur_route.cost };
+
+                if !routes.contains(&route) && !candidates.contains(&route) {
+                    candidates.push(route);
+                }
+            }
+        }
+
+        if candidates.is_empty() {
+            break;
+        }
+
+        let (best, _) = candidates
+            .iter()
+            .enumerate()
+            .min_by_key(|(_, route)| (route.cost, route.states.len()))
+            .unwrap();
+        routes.push(candidates.remove(best));
+    }
+
+    return routes;
+}
+
+trait Heat {
+    // None is drawn as black, e.g. for walls
+    fn heat(&self) -> Option<f64>;
+}
+
+impl Heat for usize {
+    fn heat(&self) -> Option<f64> {
+        return Some(*self as f64);
+    }
+}
+
+impl Heat for Terrain {
+    fn heat(&self) -> Option<f64> {
+        return self.cost().map(|cost| cost as f64);
+    }
+}
+
+const ROUTE_COLOUR: (u8, u8, u8) = (255, 255, 255);
+const WALL_COLOUR: (u8, u8, u8) = (0, 0, 0);
+
+// blue -> cyan -> green -> yellow -> red for t in [0, 1]
+fn colour_ramp(t: f64) -> (u8, u8, u8) {
+    let stops: [(f64, f64, f64); 5] = [(0., 0., 255.), (0., 255., 255.), (0., 255., 0.), (255., 255., 0.), (255., 0., 0.)];
+    let scaled = t.clamp(0., 1.) * (stops.len() - 1) as f64;
+    let k = (scaled.floor() as usize).min(stops.len() - 2);
+    let f = scaled - k as f64;
+
+    let (r0, g0, b0) = stops[k];
+    let (r1, g1, b1) = stops[k + 1];
+    return (
+        (r0 + f * (r1 - r0)).round() as u8,
+        (g0 + f * (g1 - g0)).round() as u8,
+        (b0 + f * (b1 - b0)).round() as u8,
+    );
+}
+
+impl<T: Heat + Clone + Default> Grid<T> {
+    fn colours(&self) -> Vec<(u8, u8, u8)> {
+        let heats = self.data.iter().map(|value| value.heat()).collect::<Vec<Option<f64>>>();
+        let min = heats.iter().flatten().cloned().fold(f64::INFINITY, f64::min);
+        let max = heats.iter().flatten().cloned().fold(f64::NEG_INFINITY, f64::max);
+
+        return heats.iter().map(|heat| match heat {
+            Some(heat) if max > min => colour_ramp((heat - min) / (max - min)),
+            Some(_) => colour_ramp(0.),
+            None => WALL_COLOUR,
+        }).collect();
+    }
+
+    // Binary PPM (P6), each cell is a scale x scale block; route cells get a
+    // white centre so the cost colour stays visible around it, below a scale of 3
+    // there is no room for a border and the whole cell is white
+    fn to_ppm(&self, route: Option<&Route>, scale: usize) -> Vec<u8> {
+        let scale = scale.max(1);
+        let colours = self.colours();
+        let on_route: HashSet<(usize, usize)> = route
+            .map(|route| route.states.iter().map(|state| (state.position.i, state.position.j)).collect())
+            .unwrap_or_default();
+        let margin = if scale >= 3 { (scale / 4).max(1) } else { 0 };
+
+        let mut image = format!("P6\n{} {}\n255\n", self.cols * scale, self.rows * scale).into_bytes();
+        for y in 0..self.rows * scale {
+            for x in 0..self.cols * scale {
+                let (i, j) = (y / scale, x / scale);
+                let (dy, dx) = (y % scale, x % scale);
+                let centre = dy >= margin && dy < scale - margin && dx >= margin && dx < scale - margin;
+
+                let (r, g, b) = if centre && on_route.contains(&(i, j)) {
+                    ROUTE_COLOUR
+                } else {
+                    colours[i * self.cols + j]
+                };
+                image.extend([r, g, b]);
+            }
+        }
+        return image;
+    }
+
+    fn to_svg(&self, route: Option<&Route>) -> String {
+        let colours = self.colours();
+        let mut svg = format!(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{}\" height=\"{}\" viewBox=\"0 0 {} {}\" shape-rendering=\"crispEdges\">\n",
+            self.cols * 10, self.rows * 10, self.cols, self.rows
+        );
+
+        for i in 0..self.rows {
+            for j in 0..self.cols {
+                let (r, g, b) = colours[i * self.cols + j];
+                svg.push_str(&format!("<rect x=\"{}\" y=\"{}\" width=\"1\" height=\"1\" fill=\"rgb({},{},{})\"/>\n", j, i, r, g, b));
+            }
+        }
+
+        if let Some(route) = route {
+            let points = route.states
+                .iter()
+                .map(|state| format!("{}.5,{}.5", state.position.j, state.position.i))
+                .collect::<Vec<String>>()
+                .join(" ");
+            let (r, g, b) = ROUTE_COLOUR;
+            svg.push_str(&format!(
+                "<polyline points=\"{}\" fill=\"none\" stroke=\"rgb({},{},{})\" stroke-width=\"0.3\" stroke-linejoin=\"round\"><title>cost {}</title></polyline>\n",
+                points, r, g, b, route.cost
+            ));
+        }
+
+        svg.push_str("</svg>\n");
+        return svg;
+    }
+}
+
+fn parse_terrain(token: &str, i: usize, j: usize) -> Terrain {
+    if token == "#" {
+        return Terrain::Wall;
+    }
+    match token.parse::<usize>() {
+        // the search relies on every step costing at least as much as the distance it covers
+        Ok(0) => panic!("cell {:?} at line {}, column {} costs nothing to enter", token, i + 1, j + 1),
+        Ok(cost) => Terrain::Cost(cost),
+        Err(_) => panic!("invalid cell {:?} at line {}, column {}", token, i + 1, j + 1),
+    }
+}
+
+fn is_separator(c: char) -> bool {
+    return c == ',' || c.is_whitespace();
+}
+
+// Either one character per cell (digits or `#`), or cells separated by commas and/or
+// whitespace so that costs can have multiple digits, which the caller picks for the whole file
+fn parse_terrain_line(line: &str, i: usize, separated: bool) -> Vec<Terrain> {
+    if line.contains(is_separator) != separated {
+        panic!("line {} {} separators, unlike the rest of the file", i + 1, if separated { "has no" } else { "has" });
+    }
+
+    if separated {
+        return line
+            .split(is_separator)
+            .filter(|token| !token.is_empty())
+            .enumerate()
+            .map(|(j, token)| parse_terrain(token, i, j))
+            .collect();
+    }
+
+    let mut buffer = [0; 4];
+    return line
+        .chars()
+        .enumerate()
+        .map(|(j, c)| parse_terrain(c.encode_utf8(&mut buffer), i, j))
+        .collect();
+}
+
+// a single column of separated costs needs a trailing comma on every line, like `10,`
+fn content_to_matrix(content: String) -> Grid<Terrain> {
+    let separated = content.lines().any(|line| line.trim().contains(is_separator));
+    let lines = content
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .enumerate()
+        .map(|(i, line)| parse_terrain_line(line.trim(), i, separated))
+        .collect::<Vec<Vec<Terrain>>>();
     let rows = lines.len();
     let cols = lines[0].len();
     let mut matrix = Grid::new(rows, cols);
 
     for (i, line) in lines.iter().enumerate() {
-        for (j, c) in line.chars().enumerate() {
+        if line.len() != cols {
+            panic!("line {} has {} cells, expected {}", i + 1, line.len(), cols);
+        }
+        for (j, terrain) in line.iter().enumerate() {
             let index = GridIndex{ i, j, rows, cols};
-            matrix.set(index, c.to_digit(10).unwrap() as usize);
+            matrix.set(index, *terrain);
         }
     }
     return matrix;
 }
 
-fn solve_part1(content: String) -> Option<usize> {
+fn solve_part1(content: String) -> Result<usize, SearchError> {
     let grid = content_to_matrix(content);
     println!("{}", grid);
 
     let start = State { position: GridIndex{ i: 0, j: 0, rows: grid.rows, cols: grid.cols }, direction: Direction::Right, steps: 0 };
-    let goal = State { position: GridIndex{i: grid.cols - 1, j: grid.rows - 1, rows: grid.rows, cols: grid.cols} , direction: Direction::Right, steps: 0 };
+    let goal = State { position: GridIndex{i: grid.rows - 1, j: grid.cols - 1, rows: grid.rows, cols: grid.cols} , direction: Direction::Right, steps: 0 };
 
     return search(&grid, start, &goal);
 }
@@ -251,15 +491,231 @@ fn solve_part2(content: String) -> usize {
     0
 }
 
+fn solve_routes(content: String, k: usize) -> Vec<Route> {
+    let grid = content_to_matrix(content);
+
+    let start = State { position: GridIndex{ i: 0, j: 0, rows: grid.rows, cols: grid.cols }, direction: Direction::Right, steps: 0 };
+    let goal = GridIndex{ i: grid.rows - 1, j: grid.cols - 1, rows: grid.rows, cols: grid.cols };
+
+    return k_shortest_routes(&grid, start, &goal, k);
+}
+
+fn export_images(content: String, ppm: Option<&std::path::Path>, svg: Option<&std::path::Path>, scale: usize) {
+    let grid = content_to_matrix(content);
+
+    let start = State { position: GridIndex{ i: 0, j: 0, rows: grid.rows, cols: grid.cols }, direction: Direction::Right, steps: 0 };
+    let goal = GridIndex{ i: grid.rows - 1, j: grid.cols - 1, rows: grid.rows, cols: grid.cols };
+    let route = shortest_route(&grid, start, &goal, &HashSet::new(), &HashSet::new());
+
+    if let Some(path) = ppm {
+        std::fs::write(path, grid.to_ppm(route.as_ref(), scale)).expect("could not write ppm");
+        println!("wrote {:?}", path);
+    }
+    if let Some(path) = svg {
+        std::fs::write(path, grid.to_svg(route.as_ref())).expect("could not write svg");
+        println!("wrote {:?}", path);
+    }
+}
+
 fn main() {
     let args = Cli::parse();
     println!("input: {:?}", args.path);
 
     let content = std::fs::read_to_string(&args.path).expect("could not read file");
 
-    if let Some(result) = solve_part1(content.clone()) {
-        println!("part1: {}", result);
-
+    match solve_part1(content.clone()) {
+        Ok(result) => println!("part1: {}", result),
+        Err(error) => println!("part1: {}", error),
     }
     println!("part2: {}", solve_part2(content.clone()));
+
+    if let Some(k) = args.routes {
+        for (n, route) in solve_routes(content.clone(), k).iter().enumerate() {
+            println!("route {}: cost {} {}", n + 1, route.cost, route.directions());
+        }
+    }
+
+    if args.ppm.is_some() || args.svg.is_some() {
+        export_images(content.clone(), args.ppm.as_deref(), args.svg.as_deref(), args.scale);
+    }
+}
+
+
+#[cfg(test)]
+mod route_tests {
+    use crate::*;
+
+    const EXAMPLE: &str = "2413432311323
+3215453535623
+3255245654254
+3446585845452
+4546657867536
+1438598798454
+4457876987766
+3637877979653
+4654967986887
+4564679986453
+1224686865563
+2546548887735
+4322674655533";
+
+    #[test]
+    fn best_route_matches_search() {
+        let routes = solve_routes(EXAMPLE.to_string(), 1);
+        assert_eq!(routes.len(), 1);
+        assert_eq!(routes[0].cost, 102);
+    }
+
+    #[test]
+    fn routes_are_distinct_and_ordered() {
+        let grid = content_to_matrix(EXAMPLE.to_string());
+        let routes = solve_routes(EXAMPLE.to_string(), 5);
+        assert_eq!(routes.len(), 5);
+
+        for (n, route) in routes.iter().enumerate() {
+            let cost: usize = route.states[1..].iter().map(|state| grid.cost(state.position).unwrap()).sum();
+            assert_eq!(route.cost, cost);
+
+            let unique: HashSet<State> = route.states.iter().cloned().collect();
+            assert_eq!(unique.len(), route.states.len());
+
+            if n > 0 {
+                assert!(routes[n - 1].cost <= route.cost);
+                assert!(routes[..n].iter().all(|other| other.states != route.states));
+            }
+        }
+    }
+
+    #[test]
+    fn routes_run_out_on_tiny_grid() {
+        // only right-down and down-right on a 2x2 grid
+        let routes = solve_routes("11\n11".to_string(), 10);
+        assert_eq!(routes.len(), 2);
+        assert_eq!(routes[0].cost, 2);
+    }
+}
+
+
+#[cfg(test)]
+mod terrain_tests {
+    use crate::*;
+
+    #[test]
+    fn parse_digits_and_walls() {
+        let grid = content_to_matrix("12#\n#45".to_string());
+        assert_eq!(grid.data, vec![Terrain::Cost(1), Terrain::Cost(2), Terrain::Wall, Terrain::Wall, Terrain::Cost(4), Terrain::Cost(5)]);
+    }
+
+    #[test]
+    fn parse_separated_costs() {
+        let grid = content_to_matrix("10, 200 #\n3 # 45".to_string());
+        assert_eq!((grid.rows, grid.cols), (2, 3));
+        assert_eq!(grid.data, vec![Terrain::Cost(10), Terrain::Cost(200), Terrain::Wall, Terrain::Cost(3), Terrain::Wall, Terrain::Cost(45)]);
+    }
+
+    #[test]
+    #[should_panic(expected = "line 1 has no separators, unlike the rest of the file")]
+    fn parse_rejects_mixed_formats() {
+        content_to_matrix("10\n20 3".to_string());
+    }
+
+    #[test]
+    fn parse_single_column_of_costs() {
+        let grid = content_to_matrix("10,\n20,".to_string());
+        assert_eq!((grid.rows, grid.cols), (2, 1));
+        assert_eq!(solve_part1("10,\n20,".to_string()), Ok(20));
+
+        // without commas every digit is a cell
+        assert_eq!(content_to_matrix("13\n24".to_string()).cols, 2);
+    }
+
+    #[test]
+    #[should_panic(expected = "invalid cell \"x\" at line 2, column 2")]
+    fn parse_rejects_unknown_cells() {
+        content_to_matrix("11\n1x".to_string());
+    }
+
+    #[test]
+    #[should_panic(expected = "cell \"0\" at line 1, column 1 costs nothing to enter")]
+    fn parse_rejects_free_cells() {
+        solve_part1("00009\n00009\n99990\n99999\n99991".to_string()).unwrap();
+    }
+
+    #[test]
+    fn search_routes_around_walls() {
+        let content = "1111\n###1\n1111\n1###\n1111";
+        assert_eq!(solve_part1(content.to_string()), Ok(13));
+    }
+
+    #[test]
+    fn search_reports_unreachable_goal() {
+        let content = "111\n###\n111";
+        match solve_part1(content.to_string()) {
+            Err(SearchError::Unreachable { cells_reached, .. }) => assert_eq!(cells_reached, 3),
+            other => panic!("expected unreachable, got {:?}", other),
+        }
+
+        assert!(matches!(solve_part1("11\n1#".to_string()), Err(SearchError::GoalBlocked(_))));
+    }
+}
+
+
+#[cfg(test)]
+mod image_tests {
+    use crate::*;
+
+    #[test]
+    fn colour_ramp_endpoints() {
+        assert_eq!(colour_ramp(0.), (0, 0, 255));
+        assert_eq!(colour_ramp(0.5), (0, 255, 0));
+        assert_eq!(colour_ramp(1.), (255, 0, 0));
+    }
+
+    #[test]
+    fn ppm_has_header_and_pixels() {
+        let grid = content_to_matrix("19\n#5".to_string());
+        let ppm = grid.to_ppm(None, 2);
+
+        let header = b"P6\n4 4\n255\n";
+        assert_eq!(&ppm[..header.len()], header);
+        assert_eq!(ppm.len(), header.len() + 4 * 4 * 3);
+
+        // top left pixel is the cheapest cell, bottom left is a wall
+        assert_eq!(&ppm[header.len()..header.len() + 3], &[0, 0, 255]);
+        assert_eq!(&ppm[ppm.len() - 4 * 3..ppm.len() - 3 * 3], &[0, 0, 0]);
+    }
+
+    #[test]
+    fn ppm_route_keeps_a_border_from_scale_3() {
+        let grid = content_to_matrix("11\n11".to_string());
+        let route = solve_routes("11\n11".to_string(), 1).remove(0);
+        for scale in 1..=8 {
+            let ppm = grid.to_ppm(Some(&route), scale);
+            let header = format!("P6\n{} {}\n255\n", 2 * scale, 2 * scale).len();
+            let pixel = |x: usize, y: usize| {
+                let start = header + (y * 2 * scale + x) * 3;
+                return (ppm[start], ppm[start + 1], ppm[start + 2]);
+            };
+
+            // the route starts in the top left cell
+            let centre = pixel(scale / 2, scale / 2);
+            assert_eq!(centre, ROUTE_COLOUR, "scale {}", scale);
+            if scale >= 3 {
+                assert_ne!(pixel(0, 0), ROUTE_COLOUR, "scale {}", scale);
+            } else {
+                assert_eq!(pixel(0, 0), ROUTE_COLOUR, "scale {}", scale);
+            }
+        }
+    }
+
+    #[test]
+    fn svg_draws_route() {
+        let grid = content_to_matrix("11\n11".to_string());
+        let route = solve_routes("11\n11".to_string(), 1).remove(0);
+        let svg = grid.to_svg(Some(&route));
+
+        assert_eq!(svg.matches("<rect").count(), 4);
+        assert!(svg.contains("<polyline points=\"0.5,0.5 "));
+        assert!(svg.trim_end().ends_with("</svg>"));
+    }
 }
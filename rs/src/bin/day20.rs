@@ -1,10 +1,63 @@
+#[path = "../numbers.rs"]
+mod numbers;
+
 use clap::Parser;
+use numbers::{combine_congruences, gcd, lcm};
+use std::collections::BTreeMap;
 use std::collections::HashMap;
+use std::collections::HashSet;
 use std::collections::VecDeque;
+use std::fmt;
+use std::hash::Hash;
+use std::hash::Hasher;
+use std::io::BufRead;
+use std::io::Write;
 
 #[derive(Parser)]
 struct Cli {
     path: std::path::PathBuf,
+
+    /// Print the module network as a Graphviz digraph instead of solving
+    #[arg(long)]
+    dot: bool,
+
+    /// Step through the network interactively instead of solving
+    #[arg(long)]
+    repl: bool,
+
+    /// Check the network for mistakes instead of solving
+    #[arg(long)]
+    validate: bool,
+
+    /// Break the network into binary counters and predict rx without simulating
+    #[arg(long)]
+    analyse: bool,
+
+    /// Button presses to count pulses for in part 1, or to trace
+    #[arg(long, default_value_t = 1000)]
+    presses: u128,
+
+    /// Write every pulse to a CSV file instead of solving
+    #[arg(long)]
+    trace: Option<std::path::PathBuf>,
+
+    /// Only trace pulses sent or received by these modules
+    #[arg(long, value_delimiter = ',')]
+    trace_modules: Vec<String>,
+
+    /// Only trace pulses of this level
+    #[arg(long, value_enum)]
+    trace_level: Option<Level>,
+
+    /// Press the button on up to 64 saved states at once and compare their pulse counts
+    #[arg(long, value_delimiter = ',')]
+    lanes: Vec<std::path::PathBuf>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, clap::ValueEnum)]
+enum Level {
+    Low,
+    High,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -14,17 +67,42 @@ struct Signal {
     receiver: String,
 }
 
-#[derive(Debug, PartialEq)]
+impl fmt::Display for Signal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} -{}-> {}", self.sender, if self.pulse { "high" } else { "low" }, self.receiver)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
 enum ModuleType {
     FlipFlop,
     Conjunction,
     Broadcast,
+    // any other prefix, looked up in the `ModuleRegistry`
+    Other(char),
 }
 
-trait Module {
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum ModuleState {
+    Stateless,
+    FlipFlop(bool),
+    // remembered pulse per sender, sorted by sender
+    Conjunction(Vec<(String, bool)>),
+    Or(Vec<(String, bool)>),
+    Counter { low: usize, high: usize },
+    Delay(bool),
+    // everything received so far, in order
+    Probe(Vec<(String, bool)>),
+}
+
+trait Module: fmt::Debug {
     fn receive(&mut self, signal: Signal) -> Vec<Signal>;
 
     fn add_sender(&mut self, sender: String);
+
+    fn state(&self) -> ModuleState;
+
+    fn restore_state(&mut self, state: &ModuleState) -> Result<(), String>;
 }
 
 #[derive(Debug)]
@@ -52,6 +130,20 @@ impl Module for FlipFlopModule {
     fn add_sender(&mut self, _sender: String) {
         return;
     }
+
+    fn state(&self) -> ModuleState {
+        return ModuleState::FlipFlop(self.state);
+    }
+
+    fn restore_state(&mut self, state: &ModuleState) -> Result<(), String> {
+        match state {
+            ModuleState::FlipFlop(on) => {
+                self.state = *on;
+                return Ok(());
+            },
+            _ => return Err(format!("{} is a flip-flop, got {:?}", self.name, state)),
+        }
+    }
 }
 
 #[derive(Debug)]
@@ -78,6 +170,29 @@ impl Module for ConjunctionModule {
         self.state.insert(sender, false);
         return;
     }
+
+    fn state(&self) -> ModuleState {
+        return ModuleState::Conjunction(sorted_memory(&self.state));
+    }
+
+    fn restore_state(&mut self, state: &ModuleState) -> Result<(), String> {
+        let ModuleState::Conjunction(memory) = state else {
+            return Err(format!("{} is a conjunction, got {:?}", self.name, state));
+        };
+
+        let mut senders = memory.iter().map(|(sender, _)| sender).collect::<Vec<&String>>();
+        let mut known = self.state.keys().collect::<Vec<&String>>();
+        senders.sort();
+        known.sort();
+        if senders != known {
+            return Err(format!("{} has inputs {:?}, got {:?}", self.name, known, senders));
+        }
+
+        for (sender, pulse) in memory {
+            self.state.insert(sender.clone(), *pulse);
+        }
+        return Ok(());
+    }
 }
 
 #[derive(Debug)]
@@ -95,238 +210,3016 @@ impl Module for BroadcastModule {
     fn add_sender(&mut self, _sender: String) {
         return;
     }
-}
 
+    fn state(&self) -> ModuleState {
+        return ModuleState::Stateless;
+    }
 
-fn parse_module_type(line: &str) -> ModuleType {
-    match line.chars().next().unwrap() {
-        '&' => return ModuleType::Conjunction,
-        '%' => return ModuleType::FlipFlop,
-        _ => return ModuleType::Broadcast,
+    fn restore_state(&mut self, state: &ModuleState) -> Result<(), String> {
+        match state {
+            ModuleState::Stateless => return Ok(()),
+            _ => return Err(format!("{} has no state, got {:?}", self.name, state)),
+        }
     }
 }
 
-fn parse_module_name(line: &str) -> String {
-    match parse_module_type(line) {
-        ModuleType::Broadcast => return "broadcast".to_string(),
-        _ => return line.split_whitespace().next().unwrap()[1..].to_string(),
+
+fn send(name: &str, receivers: &[String], pulse: bool) -> Vec<Signal> {
+    return receivers.iter().map(|receiver| Signal { pulse, sender: name.to_string(), receiver: receiver.clone() }).collect();
+}
+
+fn sorted_memory(memory: &HashMap<String, bool>) -> Vec<(String, bool)> {
+    let mut memory = memory.iter().map(|(sender, pulse)| (sender.clone(), *pulse)).collect::<Vec<(String, bool)>>();
+    memory.sort();
+    return memory;
+}
+
+// sends the opposite of every pulse it receives
+#[derive(Debug)]
+struct InverterModule {
+    name: String,
+    receivers: Vec<String>,
+}
+
+impl Module for InverterModule {
+    fn receive(&mut self, signal: Signal) -> Vec<Signal> {
+        return send(&self.name, &self.receivers, !signal.pulse);
+    }
+
+    fn add_sender(&mut self, _sender: String) {
+        return;
+    }
+
+    fn state(&self) -> ModuleState {
+        return ModuleState::Stateless;
+    }
+
+    fn restore_state(&mut self, state: &ModuleState) -> Result<(), String> {
+        match state {
+            ModuleState::Stateless => return Ok(()),
+            _ => return Err(format!("{} has no state, got {:?}", self.name, state)),
+        }
     }
 }
 
-fn parse_receivers(line: &str) -> Vec<String> {
-    let parts = line.split(" -> ").collect::<Vec<&str>>();
-    let receivers: Vec<String> = parts[1].split(", ").map(|s| s.to_string() ).collect();
-    return receivers;
+// like a conjunction, but sends high when any remembered pulse is high
+#[derive(Debug)]
+struct OrModule {
+    name: String,
+    receivers: Vec<String>,
+    state: HashMap<String, bool>,
 }
 
-fn create_modules(content: String) -> HashMap<String, Box<dyn Module>> {
-    let mut modules: HashMap<String, Box<dyn Module>> = HashMap::new();
+impl Module for OrModule {
+    fn receive(&mut self, signal: Signal) -> Vec<Signal> {
+        self.state.insert(signal.sender, signal.pulse);
+        return send(&self.name, &self.receivers, self.state.values().any(|pulse| *pulse));
+    }
 
-    // create a hashmap with all modules
-    for line in content.lines() {
-        let module_type = parse_module_type(line);
-        let module_name = parse_module_name(line);
-        let receivers = parse_receivers(line);
+    fn add_sender(&mut self, sender: String) {
+        self.state.insert(sender, false);
+    }
 
+    fn state(&self) -> ModuleState {
+        return ModuleState::Or(sorted_memory(&self.state));
+    }
 
-        match module_type {
-            ModuleType::Broadcast => {
-                modules.insert(module_name.to_string(), Box::new(BroadcastModule { name: "broadcast".to_string(), receivers }));
-            },
+    fn restore_state(&mut self, state: &ModuleState) -> Result<(), String> {
+        let ModuleState::Or(memory) = state else {
+            return Err(format!("{} is an or gate, got {:?}", self.name, state));
+        };
+        if memory.iter().any(|(sender, _)| !self.state.contains_key(sender)) || memory.len() != self.state.len() {
+            return Err(format!("{} has inputs {:?}, got {:?}", self.name, sorted_memory(&self.state), memory));
+        }
+        self.state = memory.iter().cloned().collect();
+        return Ok(());
+    }
+}
 
-            ModuleType::Conjunction => {
-                modules.insert(module_name.to_string(), Box::new(ConjunctionModule { name: module_name.to_string(), receivers, state: HashMap::new() }));
-            },
+// passes every pulse on and counts them
+#[derive(Debug)]
+struct CounterModule {
+    name: String,
+    receivers: Vec<String>,
+    low: usize,
+    high: usize,
+}
 
-            ModuleType::FlipFlop => {
-                modules.insert(module_name.to_string(), Box::new(FlipFlopModule { name: module_name.to_string(), receivers, state: false }));
-            },
+impl Module for CounterModule {
+    fn receive(&mut self, signal: Signal) -> Vec<Signal> {
+        if signal.pulse {
+            self.high += 1;
+        } else {
+            self.low += 1;
         }
+        return send(&self.name, &self.receivers, signal.pulse);
     }
 
-    println!("modules: {:?}", modules.keys());
+    fn add_sender(&mut self, _sender: String) {
+        return;
+    }
 
-    // add senders now that we know all modules
-    for line in content.lines() {
-        let module_name = parse_module_name(line);
-        let receivers = parse_receivers(line);
+    fn state(&self) -> ModuleState {
+        return ModuleState::Counter { low: self.low, high: self.high };
+    }
 
-        println!("{} -> {:?}", module_name, receivers);
-        for receiver in receivers {
-            // receiver might not be connected to anything
-            if let Some(module) = modules.get_mut(&receiver){
-                module.add_sender(module_name.clone());
-            }
+    fn restore_state(&mut self, state: &ModuleState) -> Result<(), String> {
+        match state {
+            ModuleState::Counter { low, high } => {
+                (self.low, self.high) = (*low, *high);
+                return Ok(());
+            },
+            _ => return Err(format!("{} is a counter, got {:?}", self.name, state)),
         }
     }
+}
 
-    return modules;
+// sends the pulse it received the time before, starting with low
+#[derive(Debug)]
+struct DelayModule {
+    name: String,
+    receivers: Vec<String>,
+    state: bool,
 }
 
-fn solve_part1(content: String) -> usize {
-    let mut modules = create_modules(content);
+impl Module for DelayModule {
+    fn receive(&mut self, signal: Signal) -> Vec<Signal> {
+        let pulse = self.state;
+        self.state = signal.pulse;
+        return send(&self.name, &self.receivers, pulse);
+    }
 
-    let mut queue: VecDeque<Signal> = VecDeque::new();
+    fn add_sender(&mut self, _sender: String) {
+        return;
+    }
 
-    let mut low_signals = 0;
-    let mut high_signals = 0;
+    fn state(&self) -> ModuleState {
+        return ModuleState::Delay(self.state);
+    }
 
+    fn restore_state(&mut self, state: &ModuleState) -> Result<(), String> {
+        match state {
+            ModuleState::Delay(pulse) => {
+                self.state = *pulse;
+                return Ok(());
+            },
+            _ => return Err(format!("{} is a delay line, got {:?}", self.name, state)),
+        }
+    }
+}
 
-    for _ in 0..1000 {
-        queue.push_back(Signal { pulse: false, sender: "button".to_string(), receiver: "broadcast".to_string() });
+// records everything it receives and passes it on unchanged
+#[derive(Debug)]
+struct ProbeModule {
+    name: String,
+    receivers: Vec<String>,
+    received: Vec<(String, bool)>,
+}
 
-        while !queue.is_empty() {
-            let signal = queue.pop_front().unwrap();
+impl Module for ProbeModule {
+    fn receive(&mut self, signal: Signal) -> Vec<Signal> {
+        self.received.push((signal.sender, signal.pulse));
+        return send(&self.name, &self.receivers, signal.pulse);
+    }
 
-            if signal.pulse {
-                high_signals += 1;
-            } else {
-                low_signals += 1;
-            }
+    fn add_sender(&mut self, _sender: String) {
+        return;
+    }
 
-            if let Some(module) = modules.get_mut(&signal.receiver) {
-                let signals = module.receive(signal);
+    fn state(&self) -> ModuleState {
+        return ModuleState::Probe(self.received.clone());
+    }
 
-                for signal in signals {
-                    queue.push_back(signal);
-                }
-            }
+    fn restore_state(&mut self, state: &ModuleState) -> Result<(), String> {
+        match state {
+            ModuleState::Probe(received) => {
+                self.received = received.clone();
+                return Ok(());
+            },
+            _ => return Err(format!("{} is a probe, got {:?}", self.name, state)),
         }
     }
+}
 
-    println!("low: {}, high: {}", low_signals, high_signals);
+type ModuleConstructor = fn(name: String, receivers: Vec<String>) -> Box<dyn Module>;
 
-    return low_signals * high_signals;
+// Maps the prefix character of a line to the module it creates, the broadcaster has no prefix
+struct ModuleRegistry {
+    constructors: HashMap<char, ModuleConstructor>,
 }
 
-fn solve_part2(content: String) -> usize {
-    let mut modules = create_modules(content);
-
-    let mut queue: VecDeque<Signal> = VecDeque::new();
+impl ModuleRegistry {
+    fn new() -> Self {
+        return ModuleRegistry { constructors: HashMap::new() };
+    }
 
-    let mut button_presses = 0;
+    fn standard() -> Self {
+        let mut registry = ModuleRegistry::new();
+        registry.register('%', |name, receivers| Box::new(FlipFlopModule { name, receivers, state: false }));
+        registry.register('&', |name, receivers| Box::new(ConjunctionModule { name, receivers, state: HashMap::new() }));
+        registry.register('!', |name, receivers| Box::new(InverterModule { name, receivers }));
+        registry.register('|', |name, receivers| Box::new(OrModule { name, receivers, state: HashMap::new() }));
+        registry.register('#', |name, receivers| Box::new(CounterModule { name, receivers, low: 0, high: 0 }));
+        registry.register('~', |name, receivers| Box::new(DelayModule { name, receivers, state: false }));
+        registry.register('?', |name, receivers| Box::new(ProbeModule { name, receivers, received: vec![] }));
+        return registry;
+    }
 
-    loop {
-        button_presses += 1;
-        queue.push_back(Signal { pulse: false, sender: "button".to_string(), receiver: "broadcast".to_string() });
+    fn register(&mut self, prefix: char, constructor: ModuleConstructor) {
+        self.constructors.insert(prefix, constructor);
+    }
 
-        while !queue.is_empty() {
-            let signal = queue.pop_front().unwrap();
+    fn knows(&self, prefix: char) -> bool {
+        return self.constructors.contains_key(&prefix);
+    }
 
-            if signal.receiver == "rx" && !signal.pulse {
-                return button_presses
-            }
+    fn create_modules(&self, content: &str) -> Result<HashMap<String, Box<dyn Module>>, String> {
+        let mut modules: HashMap<String, Box<dyn Module>> = HashMap::new();
+
+        // create a hashmap with all modules
+        for line in content.lines() {
+            let module_name = parse_module_name(line);
+            let receivers = parse_receivers(line);
+
+            let module: Box<dyn Module> = match parse_module_type(line) {
+                ModuleType::Broadcast => Box::new(BroadcastModule { name: "broadcast".to_string(), receivers }),
+                _ => {
+                    let prefix = line.chars().next().unwrap();
+                    match self.constructors.get(&prefix) {
+                        Some(constructor) => constructor(module_name.clone(), receivers),
+                        None => return Err(format!("unknown module prefix {:?} in {:?}", prefix, line)),
+                    }
+                },
+            };
+            modules.insert(module_name, module);
+        }
 
-            if let Some(module) = modules.get_mut(&signal.receiver) {
-                let signals = module.receive(signal);
+        // add senders now that we know all modules
+        for line in last_definitions(content) {
+            let module_name = parse_module_name(line);
+            let receivers = parse_receivers(line);
 
-                for signal in signals {
-                    queue.push_back(signal);
+            for receiver in receivers {
+                // receiver might not be connected to anything
+                if let Some(module) = modules.get_mut(&receiver){
+                    module.add_sender(module_name.clone());
                 }
             }
         }
-        if button_presses % 100000 == 0 {
-            println!("button presses: {}", button_presses);
-        }
+
+        return Ok(modules);
     }
 }
 
-fn main() {
-    let args = Cli::parse();
-    println!("input: {:?}", args.path);
+fn parse_module_type(line: &str) -> ModuleType {
+    match line.chars().next().unwrap() {
+        '&' => return ModuleType::Conjunction,
+        '%' => return ModuleType::FlipFlop,
+        c if c.is_alphanumeric() => return ModuleType::Broadcast,
+        c => return ModuleType::Other(c),
+    }
+}
 
-    let content = std::fs::read_to_string(&args.path).expect("could not read file");
+fn parse_module_name(line: &str) -> String {
+    match parse_module_type(line) {
+        ModuleType::Broadcast => return "broadcast".to_string(),
+        _ => return line.split_whitespace().next().unwrap()[1..].to_string(),
+    }
+}
 
-    println!("part1: {}", solve_part1(content.clone()));
-    println!("part2: {}", solve_part2(content.clone()));
+fn parse_receivers(line: &str) -> Vec<String> {
+    let parts = line.split(" -> ").collect::<Vec<&str>>();
+    let receivers: Vec<String> = parts[1].split(", ").map(|s| s.to_string() ).collect();
+    return receivers;
 }
 
+// the line defining each module, the last one when a module is defined more than once
+fn last_definitions(content: &str) -> Vec<&str> {
+    let lines = content.lines().collect::<Vec<&str>>();
+    let last = lines.iter().enumerate().map(|(n, line)| (parse_module_name(line), n)).collect::<HashMap<String, usize>>();
+    return lines.iter().enumerate().filter(|(n, line)| last[&parse_module_name(line)] == *n).map(|(_, line)| *line).collect();
+}
 
-#[cfg(test)]
-mod module_tests {
-    use crate::*;
+fn create_modules(content: String) -> HashMap<String, Box<dyn Module>> {
+    return ModuleRegistry::standard().create_modules(&content).unwrap_or_else(|error| panic!("{}", error));
+}
 
-    #[test]
-    fn broadcast_module_sends_signals() {
-        let mut broadcast_module = BroadcastModule { name: "broadcast".to_string(), receivers: vec!["a".to_string(), "b".to_string()] };
-        let signals = broadcast_module.receive(Signal { sender: "button".to_string(), receiver: "broadcast".to_string(), pulse: true});
+fn create_senders(content: &str) -> HashMap<String, Vec<String>> {
+    let mut senders: HashMap<String, Vec<String>> = HashMap::new();
 
-        assert_eq!(signals, vec![Signal { pulse: false, sender: "broadcast".to_string(), receiver: "a".to_string() }, Signal { pulse: false, sender: "broadcast".to_string(), receiver: "b".to_string() }]);
+    for line in content.lines() {
+        let module_name = parse_module_name(line);
+        for receiver in parse_receivers(line) {
+            senders.entry(receiver).or_default().push(module_name.clone());
+        }
     }
 
-    #[test]
-    fn flip_flop_module_does_not_send_on_high_signal() {
-        let mut flip_flop_module = FlipFlopModule { name: "self".to_string(), receivers: vec!["a".to_string(), "b".to_string()], state: false };
-        let signals = flip_flop_module.receive(Signal {sender: "broadcast".to_string(), receiver: "self".to_string(), pulse: true});
+    return senders;
+}
 
-        assert_eq!(signals, vec![]);
-    }
+// State of all flip-flops and conjunctions, written one module per line:
+//   %a on
+//   &con a=high b=low
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct Snapshot {
+    states: BTreeMap<String, ModuleState>,
+}
 
-    #[test]
-    fn flip_flop_module_does_send_on_low_signal() {
-        let mut flip_flop_module = FlipFlopModule { name: "ff".to_string(), receivers: vec!["a".to_string(), "b".to_string()], state: false };
-        let signals = flip_flop_module.receive(Signal {sender: "sender".to_string(), receiver: "ff".to_string(), pulse: false});
+fn pulse_name(pulse: bool) -> &'static str {
+    return if pulse { "high" } else { "low" };
+}
 
-        assert_eq!(signals, vec![Signal { pulse: true, sender: "ff".to_string(), receiver: "a".to_string() }, Signal { pulse: true, sender: "ff".to_string(), receiver: "b".to_string() }]);
+impl Snapshot {
+    fn take(modules: &HashMap<String, Box<dyn Module>>) -> Self {
+        let states = modules
+            .iter()
+            .map(|(name, module)| (name.clone(), module.state()))
+            .filter(|(_, state)| *state != ModuleState::Stateless)
+            .collect();
+        return Snapshot { states };
     }
 
-    #[test]
-    fn conjunction_module_is_inverter_with_single_input() {
-        let mut conjunction_module = ConjunctionModule { name: "cm".to_string(), receivers: vec!["a".to_string(), "b".to_string()], state: HashMap::new() };
-        // initialize state
-        conjunction_module.add_sender("sender".to_string());
-
-        // receive a message and generate signals
-        let signals = conjunction_module.receive(Signal{sender: "sender".to_string(), receiver: "cm".to_string(), pulse: true});
-
-        assert_eq!(signals, vec![Signal { pulse: false, sender: "cm".to_string(), receiver: "a".to_string() }, Signal { pulse: false, sender: "cm".to_string(), receiver: "b".to_string() }]);
+    // modules that are not in the snapshot keep their current state
+    fn restore(&self, modules: &mut HashMap<String, Box<dyn Module>>) -> Result<(), String> {
+        for (name, state) in self.states.iter() {
+            match modules.get_mut(name) {
+                Some(module) => module.restore_state(state)?,
+                None => return Err(format!("unknown module {}", name)),
+            }
+        }
+        return Ok(());
     }
 
-    #[test]
-    fn conjuction_module_handles_multiple_senders() {
-        let mut conjunction_module = ConjunctionModule { name: "cm".to_string(), receivers: vec!["a".to_string(), "b".to_string()], state: HashMap::new() };
-        // initialize state
-        conjunction_module.add_sender("alice".to_string());
-        conjunction_module.add_sender("bob".to_string());
+    fn parse(text: &str) -> Result<Self, String> {
+        let mut states = BTreeMap::new();
+
+        for (n, line) in text.lines().enumerate().filter(|(_, line)| !line.trim().is_empty()) {
+            let error = |message: &str| format!("line {}: {} in {:?}", n + 1, message, line);
+            let words = line.split_whitespace().collect::<Vec<&str>>();
+            let prefix = words[0].chars().next().unwrap();
+            let name = words[0][prefix.len_utf8()..].to_string();
+
+            let state = match prefix {
+                '%' => match words[1..] {
+                    ["on"] => ModuleState::FlipFlop(true),
+                    ["off"] => ModuleState::FlipFlop(false),
+                    _ => return Err(error("expected on or off")),
+                },
+                '&' | '|' | '?' => {
+                    let mut memory = vec![];
+                    for word in words[1..].iter() {
+                        match word.split_once('=') {
+                            Some((sender, "high")) => memory.push((sender.to_string(), true)),
+                            Some((sender, "low")) => memory.push((sender.to_string(), false)),
+                            _ => return Err(error("expected sender=high or sender=low")),
+                        }
+                    }
+                    match prefix {
+                        '&' => { memory.sort(); ModuleState::Conjunction(memory) },
+                        '|' => { memory.sort(); ModuleState::Or(memory) },
+                        _ => ModuleState::Probe(memory),
+                    }
+                },
+                '#' => {
+                    let count = |key: &str| words[1..].iter().find_map(|word| word.strip_prefix(key)?.strip_prefix('=')?.parse::<usize>().ok());
+                    match (count("low"), count("high")) {
+                        (Some(low), Some(high)) if words.len() == 3 => ModuleState::Counter { low, high },
+                        _ => return Err(error("expected low=n high=n")),
+                    }
+                },
+                '~' => match words[1..] {
+                    ["high"] => ModuleState::Delay(true),
+                    ["low"] => ModuleState::Delay(false),
+                    _ => return Err(error("expected high or low")),
+                },
+                _ => return Err(error("unknown module prefix")),
+            };
+
+            if states.insert(name, state).is_some() {
+                return Err(error("duplicate module"));
+            }
+        }
 
-        // receive a message and generate signals
-        let signals = conjunction_module.receive(Signal {sender: "alice".to_string(), receiver: "cm".to_string(),  pulse: true});
-        assert_eq!(signals, vec![Signal { pulse: true, sender: "cm".to_string(), receiver: "a".to_string() }, Signal { pulse: true, sender: "cm".to_string(), receiver: "b".to_string() }]);
+        return Ok(Snapshot { states });
+    }
 
-        let signals = conjunction_module.receive(Signal {sender: "bob".to_string(), receiver: "cm".to_string(),  pulse: true});
-        assert_eq!(signals, vec![Signal { pulse: false, sender: "cm".to_string(), receiver: "a".to_string() }, Signal { pulse: false, sender: "cm".to_string(), receiver: "b".to_string() }]);
+    fn diff(&self, other: &Snapshot) -> Vec<String> {
+        let mut names = self.states.keys().chain(other.states.keys()).collect::<Vec<&String>>();
+        names.sort();
+        names.dedup();
+
+        let mut differences = vec![];
+        for name in names {
+            match (self.states.get(name), other.states.get(name)) {
+                (Some(ModuleState::FlipFlop(a)), Some(ModuleState::FlipFlop(b))) if a != b => {
+                    differences.push(format!("%{}: {} -> {}", name, if *a { "on" } else { "off" }, if *b { "on" } else { "off" }));
+                },
+                (Some(ModuleState::Conjunction(a)), Some(ModuleState::Conjunction(b))) if a != b => {
+                    for (sender, pulse) in a.iter() {
+                        match b.iter().find(|(other_sender, _)| other_sender == sender) {
+                            Some((_, other_pulse)) if other_pulse != pulse => {
+                                differences.push(format!("&{}.{}: {} -> {}", name, sender, pulse_name(*pulse), pulse_name(*other_pulse)));
+                            },
+                            Some(_) => {},
+                            None => differences.push(format!("&{}.{}: only in first", name, sender)),
+                        }
+                    }
+                    for (sender, _) in b.iter().filter(|(sender, _)| !a.iter().any(|(other_sender, _)| other_sender == sender)) {
+                        differences.push(format!("&{}.{}: only in second", name, sender));
+                    }
+                },
+                (Some(_), None) => differences.push(format!("{}: only in first", name)),
+                (None, Some(_)) => differences.push(format!("{}: only in second", name)),
+                (a, b) if a != b => differences.push(format!("{}: {:?} -> {:?}", name, a, b)),
+                _ => {},
+            }
+        }
+        return differences;
     }
+}
 
-    #[test]
-    fn parse_receivers_correctly() {
-        let line = "broadcast -> ab, cd, ef";
-        assert_eq!(parse_receivers(line), vec!["ab", "cd", "ef"]);
+impl fmt::Display for Snapshot {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (name, state) in self.states.iter() {
+            match state {
+                ModuleState::FlipFlop(on) => writeln!(f, "%{} {}", name, if *on { "on" } else { "off" })?,
+                ModuleState::Conjunction(memory) | ModuleState::Or(memory) | ModuleState::Probe(memory) => {
+                    let prefix = match state {
+                        ModuleState::Conjunction(_) => '&',
+                        ModuleState::Or(_) => '|',
+                        _ => '?',
+                    };
+                    let memory = memory
+                        .iter()
+                        .map(|(sender, pulse)| format!("{}={}", sender, pulse_name(*pulse)))
+                        .collect::<Vec<String>>();
+                    writeln!(f, "{}{} {}", prefix, name, memory.join(" "))?
+                },
+                ModuleState::Counter { low, high } => writeln!(f, "#{} low={} high={}", name, low, high)?,
+                ModuleState::Delay(pulse) => writeln!(f, "~{} {}", name, pulse_name(*pulse))?,
+                ModuleState::Stateless => {},
+            }
+        }
+        return Ok(());
     }
+}
 
-    #[test]
-    fn parse_module_name_correctly() {
-        let line = "broadcast -> ab, cd, ef";
-        assert_eq!(parse_module_name(line), "broadcast".to_string());
+// prefixes besides % and & that `CompiledNetwork` can run
+const COMPILED_PREFIXES: [char; 5] = ['|', '!', '~', '#', '?'];
 
-        let line = "&ab -> cd, ef";
-        assert_eq!(parse_module_name(line), "ab".to_string());
+// receivers that are expected to have no module of their own
+const KNOWN_SINKS: [&str; 2] = ["rx", "output"];
 
-        let line = "%ab -> cd, ef";
-        assert_eq!(parse_module_name(line), "ab".to_string());
+// line numbers start at 1
+#[derive(Debug, PartialEq)]
+enum Finding {
+    Malformed { line: usize },
+    UnknownPrefix { line: usize, prefix: char },
+    Unsupported { line: usize, prefix: char },
+    UndefinedReceiver { line: usize, sender: String, receiver: String },
+    Sink { lines: Vec<usize>, name: String },
+    Duplicate { lines: Vec<usize>, name: String },
+    RenamedBroadcaster { line: usize, name: String },
+    Unreachable { line: usize, name: String },
+    NoInputs { line: usize, name: String },
+    MissingBroadcast,
+}
 
-        let line = "%a -> cd, ef";
-        assert_eq!(parse_module_name(line), "a".to_string());
+impl Finding {
+    // sinks are listed for information only
+    fn is_error(&self) -> bool {
+        return !matches!(self, Finding::Sink { .. });
     }
 
-    #[test]
-    fn parse_module_type_correctly() {
-        let line = "broadcast -> ab, cd, ef";
-        assert_eq!(parse_module_type(line), ModuleType::Broadcast);
+    fn first_line(&self) -> usize {
+        match self {
+            Finding::Malformed { line }
+            | Finding::UnknownPrefix { line, .. }
+            | Finding::Unsupported { line, .. }
+            | Finding::UndefinedReceiver { line, .. }
+            | Finding::RenamedBroadcaster { line, .. }
+            | Finding::Unreachable { line, .. }
+            | Finding::NoInputs { line, .. } => return *line,
+            Finding::Sink { lines, .. } | Finding::Duplicate { lines, .. } => return lines[0],
+            Finding::MissingBroadcast => return 0,
+        }
+    }
+}
 
-        let line = "&ab -> cd, ef";
-        assert_eq!(parse_module_type(line), ModuleType::Conjunction);
+fn format_lines(lines: &[usize]) -> String {
+    let numbers = lines.iter().map(|line| line.to_string()).collect::<Vec<String>>().join(", ");
+    return format!("{} {}", if lines.len() == 1 { "line" } else { "lines" }, numbers);
+}
 
-        let line = "%ab -> cd, ef";
-        assert_eq!(parse_module_type(line), ModuleType::FlipFlop);
+impl fmt::Display for Finding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Finding::Malformed { line } => write!(f, "line {}: expected `name -> receiver, ...`", line),
+            Finding::UnknownPrefix { line, prefix } => write!(f, "line {}: unknown module prefix {:?}", line, prefix),
+            Finding::Unsupported { line, prefix } => write!(f, "line {}: {:?} modules are not supported by the solver", line, prefix),
+            Finding::UndefinedReceiver { line, sender, receiver } => write!(f, "line {}: {} sends to {}, which is not defined", line, sender, receiver),
+            Finding::Sink { lines, name } => write!(f, "{}: {} is a sink", format_lines(lines), name),
+            Finding::Duplicate { lines, name } => write!(f, "{}: {} is defined more than once", format_lines(lines), name),
+            Finding::RenamedBroadcaster { line, name } => write!(f, "line {}: {} has no prefix and is treated as the broadcaster", line, name),
+            Finding::Unreachable { line, name } => write!(f, "line {}: {} is never reached from the broadcaster", line, name),
+            Finding::NoInputs { line, name } => write!(f, "line {}: conjunction {} has no inputs and always sends low", line, name),
+            Finding::MissingBroadcast => write!(f, "no broadcaster defined"),
+        }
     }
-}
\ No newline at end of file
+}
+
+fn validate_network(content: &str, registry: &ModuleRegistry, sinks: &[&str]) -> Vec<Finding> {
+    let mut findings = vec![];
+
+    // (line number, name, type, receivers) for every line that parses
+    let mut definitions: Vec<(usize, String, ModuleType, Vec<String>)> = vec![];
+    for (n, line) in content.lines().enumerate() {
+        let line_number = n + 1;
+        if line.trim().is_empty() || !line.contains(" -> ") {
+            findings.push(Finding::Malformed { line: line_number });
+            continue;
+        }
+
+        let module_type = parse_module_type(line);
+        let module_name = parse_module_name(line);
+        match module_type {
+            ModuleType::Broadcast => {
+                let word = line.split_whitespace().next().unwrap();
+                if word != "broadcaster" {
+                    findings.push(Finding::RenamedBroadcaster { line: line_number, name: word.to_string() });
+                }
+            },
+            ModuleType::FlipFlop | ModuleType::Conjunction => {},
+            ModuleType::Other(prefix) => {
+                if !registry.knows(prefix) {
+                    findings.push(Finding::UnknownPrefix { line: line_number, prefix });
+                } else if !COMPILED_PREFIXES.contains(&prefix) {
+                    findings.push(Finding::Unsupported { line: line_number, prefix });
+                }
+            },
+        }
+        definitions.push((line_number, module_name, module_type, parse_receivers(line)));
+    }
+
+    let mut defined: HashMap<&str, Vec<usize>> = HashMap::new();
+    for (line, name, _, _) in definitions.iter() {
+        defined.entry(name.as_str()).or_default().push(*line);
+    }
+    for (line, name, _, _) in definitions.iter() {
+        if defined[name.as_str()].len() > 1 && defined[name.as_str()][0] == *line {
+            findings.push(Finding::Duplicate { lines: defined[name.as_str()].clone(), name: name.clone() });
+        }
+    }
+
+    if !defined.contains_key("broadcast") {
+        findings.push(Finding::MissingBroadcast);
+    }
+
+    let mut sink_lines: BTreeMap<&str, Vec<usize>> = BTreeMap::new();
+    let mut receivers: HashSet<&str> = HashSet::new();
+    for (line, name, _, targets) in definitions.iter() {
+        for receiver in targets.iter() {
+            receivers.insert(receiver);
+            if defined.contains_key(receiver.as_str()) {
+                continue;
+            }
+            if sinks.contains(&receiver.as_str()) {
+                sink_lines.entry(receiver).or_default().push(*line);
+            } else {
+                findings.push(Finding::UndefinedReceiver { line: *line, sender: name.clone(), receiver: receiver.clone() });
+            }
+        }
+    }
+    for (name, lines) in sink_lines {
+        findings.push(Finding::Sink { lines, name: name.to_string() });
+    }
+
+    for (line, name, module_type, _) in definitions.iter() {
+        if *module_type == ModuleType::Conjunction && !receivers.contains(name.as_str()) {
+            findings.push(Finding::NoInputs { line: *line, name: name.clone() });
+        }
+    }
+
+    if defined.contains_key("broadcast") {
+        let mut reached: HashSet<&str> = HashSet::from(["broadcast"]);
+        let mut queue: VecDeque<&str> = VecDeque::from(["broadcast"]);
+        while let Some(current) = queue.pop_front() {
+            for (_, _, _, targets) in definitions.iter().filter(|(_, name, _, _)| name == current) {
+                for receiver in targets.iter() {
+                    if reached.insert(receiver) {
+                        queue.push_back(receiver);
+                    }
+                }
+            }
+        }
+
+        for (line, name, _, _) in definitions.iter() {
+            if !reached.contains(name.as_str()) && defined[name.as_str()][0] == *line {
+                findings.push(Finding::Unreachable { line: *line, name: name.clone() });
+            }
+        }
+    }
+
+    findings.sort_by_key(|finding| finding.first_line());
+    return findings;
+}
+
+fn network_to_dot(content: &str) -> String {
+    let mut dot = String::from("digraph network {\n    rankdir=LR;\n    \"button\" [shape=plaintext];\n");
+
+    let mut defined: Vec<String> = vec![];
+    for line in content.lines() {
+        let module_name = parse_module_name(line);
+        let (label, shape, colour) = match parse_module_type(line) {
+            ModuleType::FlipFlop => (format!("%{}", module_name), "box", "lightblue"),
+            ModuleType::Conjunction => (format!("&{}", module_name), "diamond", "lightsalmon"),
+            ModuleType::Broadcast => (module_name.clone(), "house", "palegreen"),
+            ModuleType::Other(prefix) => (format!("{}{}", prefix, module_name), "ellipse", "khaki"),
+        };
+        dot.push_str(&format!("    \"{}\" [label=\"{}\" shape={} style=filled fillcolor={}];\n", module_name, label, shape, colour));
+        defined.push(module_name);
+    }
+
+    // receivers without a definition of their own, like rx
+    let mut sinks: Vec<String> = vec![];
+    for line in content.lines() {
+        for receiver in parse_receivers(line) {
+            if !defined.contains(&receiver) && !sinks.contains(&receiver) {
+                dot.push_str(&format!("    \"{}\" [shape=doublecircle style=filled fillcolor=lightgrey];\n", receiver));
+                sinks.push(receiver);
+            }
+        }
+    }
+
+    dot.push_str("    \"button\" -> \"broadcast\";\n");
+    for line in content.lines() {
+        let module_name = parse_module_name(line);
+        for receiver in parse_receivers(line) {
+            dot.push_str(&format!("    \"{}\" -> \"{}\";\n", module_name, receiver));
+        }
+    }
+
+    dot.push_str("}\n");
+    return dot;
+}
+
+// reference implementation for the compiled network:
+// push the button once and let all signals settle, `observe` sees every signal before it is delivered
+#[cfg(test)]
+fn press_button(modules: &mut HashMap<String, Box<dyn Module>>, mut observe: impl FnMut(&Signal)) {
+    let mut queue: VecDeque<Signal> = VecDeque::new();
+    queue.push_back(Signal { pulse: false, sender: "button".to_string(), receiver: "broadcast".to_string() });
+
+    while let Some(signal) = queue.pop_front() {
+        observe(&signal);
+
+        if let Some(module) = modules.get_mut(&signal.receiver) {
+            let signals = module.receive(signal);
+
+            for signal in signals {
+                queue.push_back(signal);
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct CompiledSignal {
+    pulse: bool,
+    sender: u32,
+    receiver: u32,
+    // input index on the receiver, only used by conjunctions and or gates
+    slot: u32,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum CompiledModule {
+    FlipFlop { on: bool },
+    // one bit per input slot, `high` counts the set bits
+    Conjunction { memory: Vec<u64>, inputs: u32, high: u32 },
+    Or { memory: Vec<u64>, inputs: u32, high: u32 },
+    Inverter,
+    // the previous pulse, which is sent on the next one
+    Delay { pulse: bool },
+    // counters and probes only watch the pulses going through them
+    Counter,
+    Probe,
+    Broadcast,
+    Sink,
+}
+
+// The same network as `create_modules`, with module names interned to indices so that
+// pressing the button does not allocate or hash strings
+#[derive(Debug, Clone)]
+struct CompiledNetwork {
+    names: Vec<String>,
+    ids: HashMap<String, u32>,
+    modules: Vec<CompiledModule>,
+    // (receiver, slot) for every outgoing edge, in receiver order
+    outputs: Vec<Vec<(u32, u32)>>,
+    queue: VecDeque<CompiledSignal>,
+}
+
+impl CompiledNetwork {
+    fn compile(content: &str) -> Self {
+        let mut network = CompiledNetwork { names: vec![], ids: HashMap::new(), modules: vec![], outputs: vec![], queue: VecDeque::new() };
+        network.intern("button");
+
+        for line in content.lines() {
+            let id = network.intern(&parse_module_name(line)) as usize;
+            network.modules[id] = match parse_module_type(line) {
+                ModuleType::FlipFlop => CompiledModule::FlipFlop { on: false },
+                ModuleType::Conjunction => CompiledModule::Conjunction { memory: vec![], inputs: 0, high: 0 },
+                ModuleType::Broadcast => CompiledModule::Broadcast,
+                ModuleType::Other('|') => CompiledModule::Or { memory: vec![], inputs: 0, high: 0 },
+                ModuleType::Other('!') => CompiledModule::Inverter,
+                ModuleType::Other('~') => CompiledModule::Delay { pulse: false },
+                ModuleType::Other('#') => CompiledModule::Counter,
+                ModuleType::Other('?') => CompiledModule::Probe,
+                ModuleType::Other(prefix) => panic!("{:?} modules are not supported by the compiled network", prefix),
+            };
+        }
+
+        let button = network.ids["button"];
+        let broadcast = network.intern("broadcast");
+        network.connect(button, broadcast);
+
+        for line in last_definitions(content) {
+            let sender = network.ids[&parse_module_name(line)];
+            for receiver in parse_receivers(line) {
+                let receiver = network.intern(&receiver);
+                network.connect(sender, receiver);
+            }
+        }
+
+        return network;
+    }
+
+    fn intern(&mut self, name: &str) -> u32 {
+        if let Some(id) = self.ids.get(name) {
+            return *id;
+        }
+
+        let id = self.names.len() as u32;
+        self.names.push(name.to_string());
+        self.ids.insert(name.to_string(), id);
+        self.modules.push(CompiledModule::Sink);
+        self.outputs.push(vec![]);
+        return id;
+    }
+
+    fn connect(&mut self, sender: u32, receiver: u32) {
+        // a conjunction or or gate remembers one pulse per sender, even if it is listed twice
+        if let Some(&(_, slot)) = self.outputs[sender as usize].iter().find(|(other, _)| *other == receiver) {
+            self.outputs[sender as usize].push((receiver, slot));
+            return;
+        }
+
+        let slot = match &mut self.modules[receiver as usize] {
+            CompiledModule::Conjunction { memory, inputs, .. } | CompiledModule::Or { memory, inputs, .. } => {
+                let slot = *inputs;
+                *inputs += 1;
+                if memory.len() * 64 < *inputs as usize {
+                    memory.push(0);
+                }
+                slot
+            },
+            _ => 0,
+        };
+        self.outputs[sender as usize].push((receiver, slot));
+    }
+
+    // flip-flop and delay bits followed by the conjunction and or gate memories, enough to tell
+    // two states apart
+    fn state_key(&self) -> Vec<u64> {
+        let mut key = vec![];
+        let mut bits = 0;
+        for module in self.modules.iter() {
+            if let CompiledModule::FlipFlop { on: bit } | CompiledModule::Delay { pulse: bit } = module {
+                if bits % 64 == 0 {
+                    key.push(0);
+                }
+                *key.last_mut().unwrap() |= (*bit as u64) << (bits % 64);
+                bits += 1;
+            }
+        }
+        for module in self.modules.iter() {
+            if let CompiledModule::Conjunction { memory, .. } | CompiledModule::Or { memory, .. } = module {
+                key.extend(memory.iter());
+            }
+        }
+        return key;
+    }
+
+    fn id(&self, name: &str) -> Option<u32> {
+        return self.ids.get(name).cloned();
+    }
+
+    fn name(&self, id: u32) -> &str {
+        return &self.names[id as usize];
+    }
+
+    // same as `press_button`
+    fn press(&mut self, mut observe: impl FnMut(&CompiledSignal)) {
+        let button = 0;
+        let (broadcast, slot) = self.outputs[button][0];
+        self.queue.push_back(CompiledSignal { pulse: false, sender: button as u32, receiver: broadcast, slot });
+
+        while let Some(signal) = self.queue.pop_front() {
+            observe(&signal);
+
+            let pulse = match &mut self.modules[signal.receiver as usize] {
+                CompiledModule::FlipFlop { on } => {
+                    if signal.pulse {
+                        continue;
+                    }
+                    *on = !*on;
+                    *on
+                },
+                CompiledModule::Conjunction { memory, inputs, high } => {
+                    remember(memory, high, signal);
+                    *high != *inputs
+                },
+                CompiledModule::Or { memory, high, .. } => {
+                    remember(memory, high, signal);
+                    *high != 0
+                },
+                CompiledModule::Inverter => !signal.pulse,
+                CompiledModule::Delay { pulse } => std::mem::replace(pulse, signal.pulse),
+                CompiledModule::Counter | CompiledModule::Probe => signal.pulse,
+                CompiledModule::Broadcast => false,
+                CompiledModule::Sink => continue,
+            };
+
+            for &(receiver, slot) in self.outputs[signal.receiver as usize].iter() {
+                self.queue.push_back(CompiledSignal { pulse, sender: signal.receiver, receiver, slot });
+            }
+        }
+    }
+}
+
+// stores the pulse in the sender's slot and keeps `high` as the number of set bits
+fn remember(memory: &mut [u64], high: &mut u32, signal: CompiledSignal) {
+    let word = &mut memory[signal.slot as usize / 64];
+    let bit = 1u64 << (signal.slot % 64);
+    if (*word & bit != 0) != signal.pulse {
+        *word ^= bit;
+        if signal.pulse {
+            *high += 1;
+        } else {
+            *high -= 1;
+        }
+    }
+}
+
+const LANES: usize = 64;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct ParallelSignal {
+    // one bit per copy of the network, `pulse` only matters where `active` is set
+    active: u64,
+    pulse: u64,
+    sender: u32,
+    receiver: u32,
+    slot: u32,
+}
+
+// Up to 64 copies of a compiled network with their own state, one per bit of a u64, pressed in
+// lockstep. A signal only carries the lanes it was sent in, so every copy sees exactly the
+// signals it would see on its own and in the same order.
+struct ParallelNetwork {
+    network: CompiledNetwork,
+    // the sender of every conjunction and or gate input slot
+    slot_senders: Vec<Vec<u32>>,
+    flip_flops: Vec<u64>,
+    delays: Vec<u64>,
+    memories: Vec<Vec<u64>>,
+    lanes: u64,
+    low: Vec<u128>,
+    high: Vec<u128>,
+    queue: VecDeque<ParallelSignal>,
+}
+
+impl ParallelNetwork {
+    fn new(content: &str, lanes: usize) -> Self {
+        assert!((1..=LANES).contains(&lanes), "between 1 and {} lanes", LANES);
+        let network = CompiledNetwork::compile(content);
+
+        let mut slot_senders: Vec<Vec<u32>> = vec![vec![]; network.modules.len()];
+        for (sender, outputs) in network.outputs.iter().enumerate() {
+            for &(receiver, slot) in outputs.iter() {
+                if let CompiledModule::Conjunction { .. } | CompiledModule::Or { .. } = network.modules[receiver as usize] {
+                    let senders = &mut slot_senders[receiver as usize];
+                    if senders.len() <= slot as usize {
+                        senders.resize(slot as usize + 1, 0);
+                    }
+                    senders[slot as usize] = sender as u32;
+                }
+            }
+        }
+        let memories = slot_senders.iter().map(|senders| vec![0; senders.len()]).collect();
+
+        return ParallelNetwork {
+            flip_flops: vec![0; network.modules.len()],
+            delays: vec![0; network.modules.len()],
+            network,
+            slot_senders,
+            memories,
+            lanes: if lanes == LANES { u64::MAX } else { (1 << lanes) - 1 },
+            low: vec![0; lanes],
+            high: vec![0; lanes],
+            queue: VecDeque::new(),
+        };
+    }
+
+    fn set_lane(&mut self, lane: usize, snapshot: &Snapshot) -> Result<(), String> {
+        let bit = 1u64 << lane;
+        for (name, state) in snapshot.states.iter() {
+            let id = self.network.id(name).ok_or(format!("unknown module {}", name))? as usize;
+            match (&self.network.modules[id], state) {
+                (CompiledModule::FlipFlop { .. }, ModuleState::FlipFlop(on)) => {
+                    self.flip_flops[id] = (self.flip_flops[id] & !bit) | if *on { bit } else { 0 };
+                },
+                (CompiledModule::Delay { .. }, ModuleState::Delay(pulse)) => {
+                    self.delays[id] = (self.delays[id] & !bit) | if *pulse { bit } else { 0 };
+                },
+                (CompiledModule::Conjunction { .. }, ModuleState::Conjunction(memory)) | (CompiledModule::Or { .. }, ModuleState::Or(memory)) => {
+                    if memory.len() != self.slot_senders[id].len() {
+                        return Err(format!("{} has {} inputs, got {}", name, self.slot_senders[id].len(), memory.len()));
+                    }
+                    for (sender, pulse) in memory.iter() {
+                        let slot = self.network
+                            .id(sender)
+                            .and_then(|sender| self.slot_senders[id].iter().position(|other| *other == sender))
+                            .ok_or(format!("{} is not an input of {}", sender, name))?;
+                        self.memories[id][slot] = (self.memories[id][slot] & !bit) | if *pulse { bit } else { 0 };
+                    }
+                },
+                // what they have seen so far does not change what they send
+                (CompiledModule::Counter, ModuleState::Counter { .. }) | (CompiledModule::Probe, ModuleState::Probe(_)) => {},
+                _ => return Err(format!("{} cannot be set to {:?}", name, state)),
+            }
+        }
+        return Ok(());
+    }
+
+    fn lane_snapshot(&self, lane: usize) -> Snapshot {
+        let mut states = BTreeMap::new();
+        for (id, module) in self.network.modules.iter().enumerate() {
+            let state = match module {
+                CompiledModule::FlipFlop { .. } => ModuleState::FlipFlop(self.flip_flops[id] >> lane & 1 == 1),
+                CompiledModule::Delay { .. } => ModuleState::Delay(self.delays[id] >> lane & 1 == 1),
+                CompiledModule::Conjunction { .. } | CompiledModule::Or { .. } => {
+                    let mut memory = self.slot_senders[id]
+                        .iter()
+                        .zip(self.memories[id].iter())
+                        .map(|(sender, lanes)| (self.network.name(*sender).to_string(), lanes >> lane & 1 == 1))
+                        .collect::<Vec<(String, bool)>>();
+                    memory.sort();
+                    match module {
+                        CompiledModule::Conjunction { .. } => ModuleState::Conjunction(memory),
+                        _ => ModuleState::Or(memory),
+                    }
+                },
+                // counters and probes are not tracked per lane
+                _ => continue,
+            };
+            states.insert(self.network.name(id as u32).to_string(), state);
+        }
+        return Snapshot { states };
+    }
+
+    // same as `CompiledNetwork::press`, for every lane at once
+    fn press(&mut self, mut observe: impl FnMut(&ParallelSignal)) {
+        let (broadcast, slot) = self.network.outputs[0][0];
+        self.queue.push_back(ParallelSignal { active: self.lanes, pulse: 0, sender: 0, receiver: broadcast, slot });
+
+        while let Some(signal) = self.queue.pop_front() {
+            observe(&signal);
+
+            let mut lanes = signal.active;
+            while lanes != 0 {
+                let lane = lanes.trailing_zeros() as usize;
+                if signal.pulse >> lane & 1 == 1 {
+                    self.high[lane] += 1;
+                } else {
+                    self.low[lane] += 1;
+                }
+                lanes &= lanes - 1;
+            }
+
+            let receiver = signal.receiver as usize;
+            let (active, pulse) = match &self.network.modules[receiver] {
+                CompiledModule::FlipFlop { .. } => {
+                    let toggled = signal.active & !signal.pulse;
+                    if toggled == 0 {
+                        continue;
+                    }
+                    self.flip_flops[receiver] ^= toggled;
+                    (toggled, self.flip_flops[receiver] & toggled)
+                },
+                CompiledModule::Conjunction { .. } => {
+                    let memory = &mut self.memories[receiver];
+                    let slot = &mut memory[signal.slot as usize];
+                    *slot = (*slot & !signal.active) | (signal.pulse & signal.active);
+                    let all_high = memory.iter().fold(u64::MAX, |all, slot| all & slot);
+                    (signal.active, !all_high & signal.active)
+                },
+                CompiledModule::Or { .. } => {
+                    let memory = &mut self.memories[receiver];
+                    let slot = &mut memory[signal.slot as usize];
+                    *slot = (*slot & !signal.active) | (signal.pulse & signal.active);
+                    let any_high = memory.iter().fold(0, |any, slot| any | slot);
+                    (signal.active, any_high & signal.active)
+                },
+                CompiledModule::Inverter => (signal.active, !signal.pulse & signal.active),
+                CompiledModule::Delay { .. } => {
+                    let previous = self.delays[receiver] & signal.active;
+                    self.delays[receiver] = (self.delays[receiver] & !signal.active) | (signal.pulse & signal.active);
+                    (signal.active, previous)
+                },
+                CompiledModule::Counter | CompiledModule::Probe => (signal.active, signal.pulse & signal.active),
+                CompiledModule::Broadcast => (signal.active, 0),
+                CompiledModule::Sink => continue,
+            };
+
+            for &(next, slot) in self.network.outputs[receiver].iter() {
+                self.queue.push_back(ParallelSignal { active, pulse, sender: signal.receiver, receiver: next, slot });
+            }
+        }
+    }
+}
+
+fn compare_lanes(content: &str, paths: &[std::path::PathBuf], presses: usize) -> Result<Vec<String>, String> {
+    if paths.len() > LANES {
+        return Err(format!("at most {} states at once, got {}", LANES, paths.len()));
+    }
+
+    let mut network = ParallelNetwork::new(content, paths.len());
+    for (lane, path) in paths.iter().enumerate() {
+        let text = std::fs::read_to_string(path).map_err(|error| format!("could not read {:?}: {}", path, error))?;
+        let snapshot = Snapshot::parse(&text).map_err(|error| format!("{:?} {}", path, error))?;
+        network.set_lane(lane, &snapshot).map_err(|error| format!("{:?}: {}", path, error))?;
+    }
+
+    for _ in 0..presses {
+        network.press(|_| {});
+    }
+
+    // report how each final state drifted from the first one
+    let first = network.lane_snapshot(0);
+    let mut lines = vec![];
+    for (lane, path) in paths.iter().enumerate() {
+        lines.push(format!("{:?}: low {}, high {}, product {}", path, network.low[lane], network.high[lane], network.low[lane] * network.high[lane]));
+        if lane > 0 {
+            for difference in first.diff(&network.lane_snapshot(lane)) {
+                lines.push(format!("  {}", difference));
+            }
+        }
+    }
+    return Ok(lines);
+}
+
+const CYCLE_PRESS_BUDGET: usize = 1_000_000;
+const PROGRESS_INTERVAL: u128 = 10_000_000;
+
+#[derive(Debug, PartialEq)]
+struct PulseCounts {
+    low: u128,
+    high: u128,
+    // (first press of the cycle, cycle length) when the count was extrapolated
+    cycle: Option<(usize, usize)>,
+}
+
+fn hash_key(key: &[u64]) -> u64 {
+    let mut hasher = std::collections::hash_map::DefaultHasher::new();
+    key.hash(&mut hasher);
+    return hasher.finish();
+}
+
+fn network_state_after(content: &str, presses: usize) -> Vec<u64> {
+    let mut network = CompiledNetwork::compile(content);
+    for _ in 0..presses {
+        network.press(|_| {});
+    }
+    return network.state_key();
+}
+
+// Only a hash of each state is kept, so a repeat is confirmed by replaying up to the
+// earlier press before extrapolating from it
+fn count_pulses(content: &str, presses: u128, budget: usize) -> PulseCounts {
+    let mut network = CompiledNetwork::compile(content);
+
+    // cumulative (low, high) after each press
+    let mut totals: Vec<(u128, u128)> = vec![(0, 0)];
+    let mut seen: HashMap<u64, usize> = HashMap::new();
+    seen.insert(hash_key(&network.state_key()), 0);
+
+    let (mut low, mut high) = (0, 0);
+    let mut press: u128 = 0;
+    while press < presses {
+        network.press(|signal| {
+            if signal.pulse {
+                high += 1;
+            } else {
+                low += 1;
+            }
+        });
+        press += 1;
+
+        if press > budget as u128 {
+            if press == budget as u128 + 1 {
+                eprintln!("no cycle within {} presses, simulating all {} presses", budget, presses);
+                totals.clear();
+                seen.clear();
+            }
+            if press.is_multiple_of(PROGRESS_INTERVAL) {
+                eprintln!("pressed {} of {}", press, presses);
+            }
+            continue;
+        }
+
+        let current = press as usize;
+        totals.push((low, high));
+
+        let key = network.state_key();
+        let hash = hash_key(&key);
+        if let Some(&start) = seen.get(&hash) {
+            if network_state_after(content, start) == key {
+                let length = current - start;
+                let remaining = presses - press;
+                let (cycles, rest) = (remaining / length as u128, (remaining % length as u128) as usize);
+
+                let (cycle_low, cycle_high) = (low - totals[start].0, high - totals[start].1);
+                let (rest_low, rest_high) = (totals[start + rest].0 - totals[start].0, totals[start + rest].1 - totals[start].1);
+
+                return PulseCounts {
+                    low: low + cycles * cycle_low + rest_low,
+                    high: high + cycles * cycle_high + rest_high,
+                    cycle: Some((start, length)),
+                };
+            }
+        }
+        seen.insert(hash, current);
+    }
+
+    return PulseCounts { low, high, cycle: None };
+}
+
+fn solve_part1(content: String, presses: u128) -> u128 {
+    let counts = count_pulses(&content, presses, CYCLE_PRESS_BUDGET);
+
+    if let Some((start, length)) = counts.cycle {
+        println!("state repeats every {} presses from press {}", length, start);
+    }
+    println!("low: {}, high: {}", counts.low, counts.high);
+
+    return counts.low * counts.high;
+}
+
+const ANALYSIS_PRESS_BUDGET: usize = 1_000_000;
+
+#[derive(Debug, PartialEq)]
+enum AnalysisError {
+    NoRx,
+    MultipleFeeders(Vec<String>),
+    FeederNotConjunction(String),
+    FeederWithoutInputs(String),
+    NotPeriodic { input: String, first: usize, second: usize },
+    BudgetExceeded { inputs: Vec<String>, presses: usize },
+}
+
+impl fmt::Display for AnalysisError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AnalysisError::NoRx => write!(f, "no module sends to rx"),
+            AnalysisError::MultipleFeeders(feeders) => write!(f, "rx is fed by {} modules ({}), expected a single conjunction", feeders.len(), feeders.join(", ")),
+            AnalysisError::FeederNotConjunction(name) => write!(f, "rx is fed by {}, which is not a conjunction", name),
+            AnalysisError::FeederWithoutInputs(name) => write!(f, "rx is fed by {}, which has no inputs and never sends anything", name),
+            AnalysisError::NotPeriodic { input, first, second } => write!(f, "{} first sends high at press {} and next at press {}, which is not a cycle starting at 0", input, first, second),
+            AnalysisError::BudgetExceeded { inputs, presses } => write!(f, "{} did not send high twice within {} presses", inputs.join(", "), presses),
+        }
+    }
+}
+
+// rx receives a low pulse when the conjunction in front of it has seen high from all of its
+// inputs, so find the press at which each input first sends high, check it repeats with that
+// period and combine the periods
+fn analyse_rx(content: String, budget: usize) -> Result<u128, AnalysisError> {
+    let senders = create_senders(&content);
+
+    let feeders = senders.get("rx").ok_or(AnalysisError::NoRx)?;
+    if feeders.len() != 1 {
+        return Err(AnalysisError::MultipleFeeders(feeders.clone()));
+    }
+    let feeder = &feeders[0];
+
+    let is_conjunction = content
+        .lines()
+        .any(|line| parse_module_type(line) == ModuleType::Conjunction && parse_module_name(line) == *feeder);
+    if !is_conjunction {
+        return Err(AnalysisError::FeederNotConjunction(feeder.clone()));
+    }
+
+    let inputs = senders.get(feeder).cloned().unwrap_or_default();
+    if inputs.is_empty() {
+        return Err(AnalysisError::FeederWithoutInputs(feeder.clone()));
+    }
+
+    let mut network = CompiledNetwork::compile(&content);
+    let feeder_id = network.id(feeder).unwrap();
+    let mut high_presses: HashMap<u32, Vec<usize>> = inputs.iter().map(|input| (network.id(input).unwrap(), vec![])).collect();
+
+    for button_presses in 1..=budget {
+        network.press(|signal| {
+            if signal.pulse && signal.receiver == feeder_id {
+                let presses = high_presses.get_mut(&signal.sender).unwrap();
+                if presses.last() != Some(&button_presses) {
+                    presses.push(button_presses);
+                }
+            }
+        });
+
+        if high_presses.values().all(|presses| presses.len() >= 2) {
+            break;
+        }
+    }
+
+    let high_presses: HashMap<&str, Vec<usize>> = high_presses.into_iter().map(|(id, presses)| (network.name(id), presses)).collect();
+
+    let mut waiting = inputs.iter().filter(|input| high_presses[input.as_str()].len() < 2).cloned().collect::<Vec<String>>();
+    if !waiting.is_empty() {
+        waiting.sort();
+        return Err(AnalysisError::BudgetExceeded { inputs: waiting, presses: budget });
+    }
+
+    let mut result = 1;
+    for input in inputs.iter() {
+        let (first, second) = (high_presses[input.as_str()][0], high_presses[input.as_str()][1]);
+        if second != 2 * first {
+            return Err(AnalysisError::NotPeriodic { input: input.clone(), first, second });
+        }
+        result = lcm(result, first as u128);
+    }
+
+    return Ok(result);
+}
+
+fn solve_part2(content: String) -> Result<u128, AnalysisError> {
+    return analyse_rx(content, ANALYSIS_PRESS_BUDGET);
+}
+
+const CROSS_CHECK_PRESS_BUDGET: u128 = 100_000;
+
+// the counter values have to fit in a u128 with room for one carry
+const MAX_COUNTER_BITS: usize = 127;
+
+// A chain of flip-flops counting button presses in binary (the first one is bit 0), with a
+// conjunction that fires once the bits of `period` are all on and resets the counter by
+// sending low to some of the flip-flops
+#[derive(Debug, Clone, PartialEq)]
+struct Counter {
+    flip_flops: Vec<String>,
+    conjunction: String,
+    // the single-input conjunction between `conjunction` and the hub in front of rx
+    inverter: String,
+    period: u128,
+    reset: u128,
+}
+
+impl Counter {
+    // presses between two firings
+    fn cycle(&self) -> u128 {
+        let size = 1u128 << self.flip_flops.len();
+        return match (self.period + size - self.reset) % size {
+            0 => size,
+            cycle => cycle,
+        };
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum StructureError {
+    NoBroadcast,
+    NotAFlipFlop(String),
+    BranchingChain(String),
+    NoConjunction(String),
+    ChainTooLong { head: String, length: usize },
+    SeveralConjunctions(String),
+    UnexpectedOutputs { conjunction: String, outputs: Vec<String> },
+    NoHub(String),
+    IrregularReset { conjunction: String, period: u128, reset: u128 },
+    NoCommonPress,
+    TooManyPresses,
+}
+
+impl fmt::Display for StructureError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StructureError::NoBroadcast => write!(f, "no broadcaster defined"),
+            StructureError::NotAFlipFlop(name) => write!(f, "{} is not a flip-flop, so it cannot be part of a counter", name),
+            StructureError::BranchingChain(name) => write!(f, "{} sends to more than one flip-flop", name),
+            StructureError::NoConjunction(name) => write!(f, "the counter starting at {} feeds no conjunction", name),
+            StructureError::ChainTooLong { head, length } => write!(f, "the counter starting at {} has {} flip-flops, at most {} are supported", head, length, MAX_COUNTER_BITS),
+            StructureError::SeveralConjunctions(name) => write!(f, "the counter starting at {} feeds more than one conjunction", name),
+            StructureError::UnexpectedOutputs { conjunction, outputs } => write!(f, "{} should send to its counter and one inverter, but also sends to {}", conjunction, outputs.join(", ")),
+            StructureError::NoHub(name) => write!(f, "{} does not lead to a single conjunction in front of rx", name),
+            StructureError::IrregularReset { conjunction, period, reset } => write!(f, "{} fires at {} and resets to {}, after which it fires at a different value", conjunction, period, reset),
+            StructureError::NoCommonPress => write!(f, "the counters never fire on the same press"),
+            StructureError::TooManyPresses => write!(f, "the counters only fire on the same press after more than 2^127 presses"),
+        }
+    }
+}
+
+// Counting up from the reset value has to reach the period before any other value with the
+// same bits on, otherwise the conjunction fires somewhere else. The period is the smallest
+// such value and all bits on the largest, so below the period it always does, and from the
+// period upwards only when the counter wraps around right away.
+fn fires_at_period(period: u128, reset: u128, size: u128) -> bool {
+    return reset < period || reset == size - 1;
+}
+
+fn find_counters(content: &str) -> Result<Vec<Counter>, StructureError> {
+    let definitions: HashMap<String, (ModuleType, Vec<String>)> = content
+        .lines()
+        .map(|line| (parse_module_name(line), (parse_module_type(line), parse_receivers(line))))
+        .collect();
+    let senders = create_senders(content);
+    let is_type = |name: &String, module_type: ModuleType| definitions.get(name).is_some_and(|(other, _)| *other == module_type);
+
+    let (_, heads) = definitions.get("broadcast").ok_or(StructureError::NoBroadcast)?;
+    let mut counters = vec![];
+    let mut hub: Option<String> = None;
+
+    for head in heads {
+        if !is_type(head, ModuleType::FlipFlop) {
+            return Err(StructureError::NotAFlipFlop(head.clone()));
+        }
+
+        let mut flip_flops = vec![head.clone()];
+        let mut conjunctions: Vec<String> = vec![];
+        loop {
+            let (_, receivers) = &definitions[flip_flops.last().unwrap()];
+            for receiver in receivers.iter().filter(|receiver| is_type(receiver, ModuleType::Conjunction)) {
+                if !conjunctions.contains(receiver) {
+                    conjunctions.push(receiver.clone());
+                }
+            }
+
+            let next = receivers.iter().filter(|receiver| is_type(receiver, ModuleType::FlipFlop)).collect::<Vec<&String>>();
+            match next[..] {
+                [] => break,
+                [next] if !flip_flops.contains(next) => flip_flops.push(next.clone()),
+                _ => return Err(StructureError::BranchingChain(flip_flops.last().unwrap().clone())),
+            }
+        }
+        if flip_flops.len() > MAX_COUNTER_BITS {
+            return Err(StructureError::ChainTooLong { head: head.clone(), length: flip_flops.len() });
+        }
+
+        let conjunction = match &conjunctions[..] {
+            [] => return Err(StructureError::NoConjunction(head.clone())),
+            [conjunction] => conjunction.clone(),
+            _ => return Err(StructureError::SeveralConjunctions(head.clone())),
+        };
+
+        let bit = |name: &String| 1u128 << flip_flops.iter().position(|flip_flop| flip_flop == name).unwrap();
+        let period: u128 = flip_flops
+            .iter()
+            .filter(|flip_flop| definitions[*flip_flop].1.contains(&conjunction))
+            .map(bit)
+            .sum();
+
+        let (_, outputs) = &definitions[&conjunction];
+        let reset_pulses: u128 = outputs.iter().filter(|output| flip_flops.contains(output)).map(bit).sum();
+        let others = outputs.iter().filter(|output| !flip_flops.contains(output)).cloned().collect::<Vec<String>>();
+        let inverter = match &others[..] {
+            [inverter] if is_type(inverter, ModuleType::Conjunction) && senders[inverter].len() == 1 => inverter.clone(),
+            _ => return Err(StructureError::UnexpectedOutputs { conjunction, outputs: others }),
+        };
+
+        match &definitions[&inverter].1[..] {
+            [next] if is_type(next, ModuleType::Conjunction) && definitions[next].1 == ["rx"] && hub.as_ref().is_none_or(|hub| hub == next) => {
+                hub = Some(next.clone());
+            },
+            _ => return Err(StructureError::NoHub(inverter)),
+        }
+
+        let size = 1u128 << flip_flops.len();
+        let reset = (period + reset_pulses) % size;
+        if !fires_at_period(period, reset, size) {
+            return Err(StructureError::IrregularReset { conjunction, period, reset });
+        }
+
+        counters.push(Counter { flip_flops, conjunction, inverter, period, reset });
+    }
+
+    if let Some(hub) = hub {
+        let mut inputs = senders[&hub].clone();
+        let mut inverters = counters.iter().map(|counter| counter.inverter.clone()).collect::<Vec<String>>();
+        inputs.sort();
+        inverters.sort();
+        if inputs != inverters {
+            return Err(StructureError::NoHub(hub));
+        }
+    }
+
+    return Ok(counters);
+}
+
+// rx receives low on the first press where every counter fires
+fn predict_rx(counters: &[Counter]) -> Result<u128, StructureError> {
+    let mut combined: (i128, i128) = (0, 1);
+    for counter in counters {
+        let cycle = i128::try_from(counter.cycle()).map_err(|_| StructureError::TooManyPresses)?;
+        let period = counter.period as i128 % cycle;
+
+        // tell a combined cycle that does not fit apart from counters that never line up
+        let (m, n) = (combined.1, cycle);
+        if (m / gcd(m as u128, n as u128) as i128).checked_mul(n).is_none() {
+            return Err(StructureError::TooManyPresses);
+        }
+        combined = combine_congruences(combined, (period, cycle)).ok_or(StructureError::NoCommonPress)?;
+    }
+
+    let (x, modulus) = (combined.0 as u128, combined.1 as u128);
+    let first = counters.iter().map(|counter| counter.period).max().unwrap_or(0);
+    if x >= first && x > 0 {
+        return Ok(x);
+    }
+    let steps = (first.saturating_sub(x)).div_ceil(modulus).max(1);
+    return Ok(x + steps * modulus);
+}
+
+// simulate up to `budget` presses and compare when each conjunction actually fires with the
+// first two firings the counters predict
+fn cross_check_counters(content: &str, counters: &[Counter], budget: u128) -> (u128, Vec<String>) {
+    let presses = counters.iter().map(|counter| counter.period + counter.cycle()).max().unwrap_or(0).min(budget);
+
+    let mut network = CompiledNetwork::compile(content);
+    let ids = counters.iter().map(|counter| network.id(&counter.conjunction).unwrap()).collect::<Vec<u32>>();
+    let mut fired: Vec<Vec<u128>> = vec![vec![]; counters.len()];
+
+    for press in 1..=presses {
+        network.press(|signal| {
+            if !signal.pulse {
+                if let Some(k) = ids.iter().position(|id| *id == signal.sender) {
+                    if fired[k].last() != Some(&press) {
+                        fired[k].push(press);
+                    }
+                }
+            }
+        });
+    }
+
+    let mut mismatches = vec![];
+    for (counter, fired) in counters.iter().zip(fired.iter()) {
+        let expected = [counter.period, counter.period + counter.cycle()]
+            .into_iter()
+            .filter(|press| *press <= presses)
+            .collect::<Vec<u128>>();
+        let actual = fired.iter().take(expected.len()).cloned().collect::<Vec<u128>>();
+        if actual != expected {
+            mismatches.push(format!("{} fired at presses {:?}, expected {:?}", counter.conjunction, actual, expected));
+        }
+    }
+    return (presses, mismatches);
+}
+
+fn analyse_counters(content: &str) -> Result<String, StructureError> {
+    let counters = find_counters(content)?;
+    let mut lines = vec![];
+
+    for counter in counters.iter() {
+        lines.push(format!(
+            "{} flip-flops from {} into &{}: fires at press {}, resets to {}, then fires every {} presses",
+            counter.flip_flops.len(), counter.flip_flops[0], counter.conjunction, counter.period, counter.reset, counter.cycle()
+        ));
+    }
+    lines.push(format!("rx receives low at press {}", predict_rx(&counters)?));
+
+    let (presses, mismatches) = cross_check_counters(content, &counters, CROSS_CHECK_PRESS_BUDGET);
+    if mismatches.is_empty() {
+        lines.push(format!("cross-check over {} presses: ok", presses));
+    } else {
+        lines.push(format!("cross-check over {} presses failed:", presses));
+        lines.extend(mismatches);
+    }
+
+    return Ok(lines.join("\n"));
+}
+
+#[derive(Debug, Default)]
+struct TraceFilter {
+    // empty means all modules
+    modules: Vec<String>,
+    level: Option<Level>,
+}
+
+impl TraceFilter {
+    fn matches(&self, sender: &str, receiver: &str, pulse: bool) -> bool {
+        let module_matches = self.modules.is_empty() || self.modules.iter().any(|module| module == sender || module == receiver);
+        let level_matches = match self.level {
+            Some(Level::High) => pulse,
+            Some(Level::Low) => !pulse,
+            None => true,
+        };
+        return module_matches && level_matches;
+    }
+}
+
+#[derive(Debug, Default, Clone, PartialEq)]
+struct ModuleCounts {
+    sent_low: usize,
+    sent_high: usize,
+    received_low: usize,
+    received_high: usize,
+}
+
+// writes `press,seq,sender,receiver,level` for every pulse that passes the filter, where
+// seq counts all pulses within the press from 0, and returns counts of the written pulses
+fn record_trace(content: &str, presses: usize, filter: &TraceFilter, out: &mut impl Write) -> std::io::Result<BTreeMap<String, ModuleCounts>> {
+    let mut network = CompiledNetwork::compile(content);
+    let names = network.names.clone();
+    let mut summary: BTreeMap<String, ModuleCounts> = BTreeMap::new();
+
+    writeln!(out, "press,seq,sender,receiver,level")?;
+    for press in 1..=presses {
+        let mut seq = 0;
+        let mut result = Ok(());
+        network.press(|signal| {
+            let (sender, receiver) = (&names[signal.sender as usize], &names[signal.receiver as usize]);
+            if filter.matches(sender, receiver, signal.pulse) && result.is_ok() {
+                result = writeln!(out, "{},{},{},{},{}", press, seq, sender, receiver, pulse_name(signal.pulse));
+
+                let sent = summary.entry(sender.clone()).or_default();
+                if signal.pulse { sent.sent_high += 1 } else { sent.sent_low += 1 }
+                let received = summary.entry(receiver.clone()).or_default();
+                if signal.pulse { received.received_high += 1 } else { received.received_low += 1 }
+            }
+            seq += 1;
+        });
+        result?;
+    }
+
+    return Ok(summary);
+}
+
+fn format_trace_summary(summary: &BTreeMap<String, ModuleCounts>) -> String {
+    let mut lines = vec![format!("{:<12} {:>10} {:>10} {:>10} {:>10}", "module", "sent low", "sent high", "recv low", "recv high")];
+    for (name, counts) in summary.iter() {
+        lines.push(format!(
+            "{:<12} {:>10} {:>10} {:>10} {:>10}",
+            name, counts.sent_low, counts.sent_high, counts.received_low, counts.received_high
+        ));
+    }
+    return lines.join("\n");
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Watch {
+    Sends,
+    Receives,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Breakpoint {
+    module: String,
+    watch: Watch,
+    pulse: bool,
+}
+
+impl Breakpoint {
+    fn parse(args: &[&str]) -> Option<Self> {
+        let [module, watch, pulse] = args else { return None };
+        let watch = match *watch {
+            "sends" => Watch::Sends,
+            "receives" => Watch::Receives,
+            _ => return None,
+        };
+        let pulse = match *pulse {
+            "high" => true,
+            "low" => false,
+            _ => return None,
+        };
+        return Some(Breakpoint { module: module.to_string(), watch, pulse });
+    }
+
+    fn matches(&self, signal: &Signal) -> bool {
+        let module = match self.watch {
+            Watch::Sends => &signal.sender,
+            Watch::Receives => &signal.receiver,
+        };
+        return *module == self.module && signal.pulse == self.pulse;
+    }
+}
+
+impl fmt::Display for Breakpoint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let watch = match self.watch {
+            Watch::Sends => "sends",
+            Watch::Receives => "receives",
+        };
+        write!(f, "{} {} {}", self.module, watch, if self.pulse { "high" } else { "low" })
+    }
+}
+
+const REPL_HELP: &str = "commands:
+  press [n]                          press the button n times, stopping at breakpoints
+  step [n]                           deliver the next n signals, pressing the button when idle
+  show <module>                      print the state of a module
+  queue                              print the signals waiting to be delivered
+  status                             print press and pulse counts
+  break <module> sends|receives high|low
+  breaks                             list breakpoints
+  delete <n>                         remove breakpoint n
+  snapshot                           print the state of all modules
+  save <file>                        write the state of all modules to a file
+  load <file>                        restore the state of all modules from a file
+  diff <file>                        compare the current state with a saved one
+  quit";
+
+// the same loop as `press_button`, but one signal at a time so it can be inspected in between
+struct Simulator {
+    modules: HashMap<String, Box<dyn Module>>,
+    queue: VecDeque<Signal>,
+    presses: usize,
+    low_signals: usize,
+    high_signals: usize,
+    breakpoints: Vec<Breakpoint>,
+}
+
+impl Simulator {
+    fn new(content: String) -> Self {
+        return Simulator {
+            modules: create_modules(content),
+            queue: VecDeque::new(),
+            presses: 0,
+            low_signals: 0,
+            high_signals: 0,
+            breakpoints: vec![],
+        };
+    }
+
+    fn step(&mut self) -> Signal {
+        if self.queue.is_empty() {
+            self.presses += 1;
+            self.queue.push_back(Signal { pulse: false, sender: "button".to_string(), receiver: "broadcast".to_string() });
+        }
+
+        let signal = self.queue.pop_front().unwrap();
+        if signal.pulse {
+            self.high_signals += 1;
+        } else {
+            self.low_signals += 1;
+        }
+
+        if let Some(module) = self.modules.get_mut(&signal.receiver) {
+            for next in module.receive(signal.clone()) {
+                self.queue.push_back(next);
+            }
+        }
+
+        return signal;
+    }
+
+    fn breakpoint_hit(&self, signal: &Signal) -> Option<usize> {
+        return self.breakpoints.iter().position(|breakpoint| breakpoint.matches(signal));
+    }
+
+    fn describe_hit(&self, hit: usize, signal: &Signal) -> String {
+        return format!("breakpoint {} ({}) at press {}: {}", hit + 1, self.breakpoints[hit], self.presses, signal);
+    }
+
+    fn press(&mut self, n: usize) -> String {
+        // finish the current press first when stopped halfway through one
+        let target = self.presses + n;
+        while !(self.queue.is_empty() && self.presses == target) {
+            let signal = self.step();
+            if let Some(hit) = self.breakpoint_hit(&signal) {
+                return self.describe_hit(hit, &signal);
+            }
+        }
+        return format!("pressed {} times", self.presses);
+    }
+
+    fn step_signals(&mut self, n: usize) -> String {
+        let mut lines = vec![];
+        for _ in 0..n {
+            let signal = self.step();
+            lines.push(format!("press {}: {}", self.presses, signal));
+            if let Some(hit) = self.breakpoint_hit(&signal) {
+                lines.push(self.describe_hit(hit, &signal));
+                break;
+            }
+        }
+        return lines.join("\n");
+    }
+
+    fn read_snapshot(&self, path: &str) -> Result<Snapshot, String> {
+        let text = std::fs::read_to_string(path).map_err(|error| format!("could not read {}: {}", path, error))?;
+        return Snapshot::parse(&text);
+    }
+
+    fn execute(&mut self, line: &str) -> String {
+        let words = line.split_whitespace().collect::<Vec<&str>>();
+        let count = |default: usize| -> Option<usize> {
+            match words.get(1) {
+                Some(word) => word.parse::<usize>().ok(),
+                None => Some(default),
+            }
+        };
+
+        match words.first() {
+            Some(&"press") => match count(1) {
+                Some(n) => self.press(n),
+                None => "usage: press [n]".to_string(),
+            },
+            Some(&"step") => match count(1) {
+                Some(n) => self.step_signals(n),
+                None => "usage: step [n]".to_string(),
+            },
+            Some(&"show") => match words.get(1).and_then(|name| self.modules.get(*name)) {
+                Some(module) => format!("{:?}", module),
+                None => "usage: show <module>".to_string(),
+            },
+            Some(&"queue") => {
+                if self.queue.is_empty() {
+                    return "queue is empty".to_string();
+                }
+                return self.queue.iter().map(|signal| signal.to_string()).collect::<Vec<String>>().join("\n");
+            },
+            Some(&"status") => format!(
+                "presses: {}, low: {}, high: {}, queued: {}",
+                self.presses, self.low_signals, self.high_signals, self.queue.len()
+            ),
+            Some(&"break") => match Breakpoint::parse(&words[1..]) {
+                Some(breakpoint) => {
+                    self.breakpoints.push(breakpoint);
+                    format!("breakpoint {}: {}", self.breakpoints.len(), self.breakpoints.last().unwrap())
+                },
+                None => "usage: break <module> sends|receives high|low".to_string(),
+            },
+            Some(&"breaks") => self.breakpoints
+                .iter()
+                .enumerate()
+                .map(|(k, breakpoint)| format!("{}: {}", k + 1, breakpoint))
+                .collect::<Vec<String>>()
+                .join("\n"),
+            Some(&"delete") => match count(0) {
+                Some(k) if k >= 1 && k <= self.breakpoints.len() => format!("deleted {}", self.breakpoints.remove(k - 1)),
+                _ => "usage: delete <n>".to_string(),
+            },
+            Some(&"snapshot") => Snapshot::take(&self.modules).to_string(),
+            Some(&"save") => match words.get(1) {
+                Some(path) => match std::fs::write(path, Snapshot::take(&self.modules).to_string()) {
+                    Ok(()) => format!("saved state after press {} to {}", self.presses, path),
+                    Err(error) => format!("could not write {}: {}", path, error),
+                },
+                None => "usage: save <file>".to_string(),
+            },
+            Some(&"load") => match words.get(1) {
+                Some(path) => match self.read_snapshot(path).and_then(|snapshot| snapshot.restore(&mut self.modules)) {
+                    Ok(()) => {
+                        self.queue.clear();
+                        format!("loaded state from {}", path)
+                    },
+                    Err(error) => error,
+                },
+                None => "usage: load <file>".to_string(),
+            },
+            Some(&"diff") => match words.get(1) {
+                Some(path) => match self.read_snapshot(path) {
+                    Ok(snapshot) => {
+                        let differences = snapshot.diff(&Snapshot::take(&self.modules));
+                        if differences.is_empty() {
+                            return "no differences".to_string();
+                        }
+                        differences.join("\n")
+                    },
+                    Err(error) => error,
+                },
+                None => "usage: diff <file>".to_string(),
+            },
+            Some(&"help") => REPL_HELP.to_string(),
+            Some(command) => format!("unknown command {:?}, try help", command),
+            None => "".to_string(),
+        }
+    }
+}
+
+fn run_repl(content: String) {
+    let mut simulator = Simulator::new(content);
+    let stdin = std::io::stdin();
+
+    print!("> ");
+    std::io::stdout().flush().unwrap();
+    for line in stdin.lock().lines() {
+        let line = line.expect("could not read line");
+        if matches!(line.trim(), "quit" | "exit") {
+            break;
+        }
+
+        let output = simulator.execute(&line);
+        if !output.is_empty() {
+            println!("{}", output);
+        }
+        print!("> ");
+        std::io::stdout().flush().unwrap();
+    }
+}
+
+fn main() {
+    let args = Cli::parse();
+    let content = std::fs::read_to_string(&args.path).expect("could not read file");
+
+    if args.dot {
+        print!("{}", network_to_dot(&content));
+        return;
+    }
+
+    if args.repl {
+        run_repl(content);
+        return;
+    }
+
+    // every other mode runs the network, which panics on the mistakes validation reports
+    let findings = validate_network(&content, &ModuleRegistry::standard(), &KNOWN_SINKS);
+    if args.validate {
+        for finding in findings.iter() {
+            println!("{}", finding);
+        }
+        if findings.iter().any(|finding| finding.is_error()) {
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    if findings.iter().any(|finding| finding.is_error()) {
+        for finding in findings.iter().filter(|finding| finding.is_error()) {
+            eprintln!("error: {}", finding);
+        }
+        std::process::exit(1);
+    }
+
+    if !args.lanes.is_empty() {
+        match compare_lanes(&content, &args.lanes, args.presses as usize) {
+            Ok(lines) => println!("{}", lines.join("\n")),
+            Err(error) => println!("{}", error),
+        }
+        return;
+    }
+
+    if args.analyse {
+        match analyse_counters(&content) {
+            Ok(report) => println!("{}", report),
+            Err(error) => println!("{}", error),
+        }
+        return;
+    }
+
+    if let Some(path) = args.trace {
+        let filter = TraceFilter { modules: args.trace_modules, level: args.trace_level };
+        let file = std::fs::File::create(&path).expect("could not create trace file");
+        let mut out = std::io::BufWriter::new(file);
+
+        let summary = record_trace(&content, args.presses as usize, &filter, &mut out).expect("could not write trace");
+        out.flush().expect("could not write trace");
+        println!("wrote {} presses to {:?}", args.presses, path);
+        println!("{}", format_trace_summary(&summary));
+        return;
+    }
+
+    println!("input: {:?}", args.path);
+
+    println!("part1: {}", solve_part1(content.clone(), args.presses));
+    match solve_part2(content.clone()) {
+        Ok(result) => println!("part2: {}", result),
+        Err(error) => println!("part2: {}", error),
+    }
+}
+
+
+// the puzzle examples and the random number generator shared by the day20 tests
+#[cfg(test)]
+mod fixtures {
+    pub const FIRST_EXAMPLE: &str = "broadcaster -> a, b, c\n%a -> b\n%b -> c\n%c -> inv\n&inv -> a";
+    pub const SECOND_EXAMPLE: &str = "broadcaster -> a\n%a -> inv, con\n&inv -> b\n%b -> con\n&con -> output";
+
+    pub fn xorshift(seed: &mut u64) -> u64 {
+        *seed ^= *seed << 13;
+        *seed ^= *seed >> 7;
+        *seed ^= *seed << 17;
+        return *seed;
+    }
+}
+
+#[cfg(test)]
+mod module_tests {
+    use crate::*;
+
+    #[test]
+    fn broadcast_module_sends_signals() {
+        let mut broadcast_module = BroadcastModule { name: "broadcast".to_string(), receivers: vec!["a".to_string(), "b".to_string()] };
+        let signals = broadcast_module.receive(Signal { sender: "button".to_string(), receiver: "broadcast".to_string(), pulse: true});
+
+        assert_eq!(signals, vec![Signal { pulse: false, sender: "broadcast".to_string(), receiver: "a".to_string() }, Signal { pulse: false, sender: "broadcast".to_string(), receiver: "b".to_string() }]);
+    }
+
+    #[test]
+    fn flip_flop_module_does_not_send_on_high_signal() {
+        let mut flip_flop_module = FlipFlopModule { name: "self".to_string(), receivers: vec!["a".to_string(), "b".to_string()], state: false };
+        let signals = flip_flop_module.receive(Signal {sender: "broadcast".to_string(), receiver: "self".to_string(), pulse: true});
+
+        assert_eq!(signals, vec![]);
+    }
+
+    #[test]
+    fn flip_flop_module_does_send_on_low_signal() {
+        let mut flip_flop_module = FlipFlopModule { name: "ff".to_string(), receivers: vec!["a".to_string(), "b".to_string()], state: false };
+        let signals = flip_flop_module.receive(Signal {sender: "sender".to_string(), receiver: "ff".to_string(), pulse: false});
+
+        assert_eq!(signals, vec![Signal { pulse: true, sender: "ff".to_string(), receiver: "a".to_string() }, Signal { pulse: true, sender: "ff".to_string(), receiver: "b".to_string() }]);
+    }
+
+    #[test]
+    fn conjunction_module_is_inverter_with_single_input() {
+        let mut conjunction_module = ConjunctionModule { name: "cm".to_string(), receivers: vec!["a".to_string(), "b".to_string()], state: HashMap::new() };
+        // initialize state
+        conjunction_module.add_sender("sender".to_string());
+
+        // receive a message and generate signals
+        let signals = conjunction_module.receive(Signal{sender: "sender".to_string(), receiver: "cm".to_string(), pulse: true});
+
+        assert_eq!(signals, vec![Signal { pulse: false, sender: "cm".to_string(), receiver: "a".to_string() }, Signal { pulse: false, sender: "cm".to_string(), receiver: "b".to_string() }]);
+    }
+
+    #[test]
+    fn conjuction_module_handles_multiple_senders() {
+        let mut conjunction_module = ConjunctionModule { name: "cm".to_string(), receivers: vec!["a".to_string(), "b".to_string()], state: HashMap::new() };
+        // initialize state
+        conjunction_module.add_sender("alice".to_string());
+        conjunction_module.add_sender("bob".to_string());
+
+        // receive a message and generate signals
+        let signals = conjunction_module.receive(Signal {sender: "alice".to_string(), receiver: "cm".to_string(),  pulse: true});
+        assert_eq!(signals, vec![Signal { pulse: true, sender: "cm".to_string(), receiver: "a".to_string() }, Signal { pulse: true, sender: "cm".to_string(), receiver: "b".to_string() }]);
+
+        let signals = conjunction_module.receive(Signal {sender: "bob".to_string(), receiver: "cm".to_string(),  pulse: true});
+        assert_eq!(signals, vec![Signal { pulse: false, sender: "cm".to_string(), receiver: "a".to_string() }, Signal { pulse: false, sender: "cm".to_string(), receiver: "b".to_string() }]);
+    }
+
+    #[test]
+    fn parse_receivers_correctly() {
+        let line = "broadcast -> ab, cd, ef";
+        assert_eq!(parse_receivers(line), vec!["ab", "cd", "ef"]);
+    }
+
+    #[test]
+    fn parse_module_name_correctly() {
+        let line = "broadcast -> ab, cd, ef";
+        assert_eq!(parse_module_name(line), "broadcast".to_string());
+
+        let line = "&ab -> cd, ef";
+        assert_eq!(parse_module_name(line), "ab".to_string());
+
+        let line = "%ab -> cd, ef";
+        assert_eq!(parse_module_name(line), "ab".to_string());
+
+        let line = "%a -> cd, ef";
+        assert_eq!(parse_module_name(line), "a".to_string());
+    }
+
+    #[test]
+    fn parse_module_type_correctly() {
+        let line = "broadcast -> ab, cd, ef";
+        assert_eq!(parse_module_type(line), ModuleType::Broadcast);
+
+        let line = "&ab -> cd, ef";
+        assert_eq!(parse_module_type(line), ModuleType::Conjunction);
+
+        let line = "%ab -> cd, ef";
+        assert_eq!(parse_module_type(line), ModuleType::FlipFlop);
+    }
+}
+
+
+#[cfg(test)]
+mod analysis_tests {
+    use crate::*;
+
+    // ca sends high every 2 presses, cb every 4
+    const COUNTERS: &str = "broadcaster -> a, b
+%a -> ca
+&ca -> hub
+%b -> b2
+%b2 -> cb
+&cb -> hub
+&hub -> rx";
+
+    fn press_until_rx_low(content: String, max_presses: usize) -> Option<usize> {
+        let mut modules = create_modules(content);
+        for button_presses in 1..=max_presses {
+            let mut rx_low = false;
+            press_button(&mut modules, |signal| rx_low |= signal.receiver == "rx" && !signal.pulse);
+            if rx_low {
+                return Some(button_presses);
+            }
+        }
+        return None;
+    }
+
+    #[test]
+    fn lcm_of_input_cycles() {
+        assert_eq!(solve_part2(COUNTERS.to_string()), Ok(4));
+        assert_eq!(press_until_rx_low(COUNTERS.to_string(), 100), Some(4));
+    }
+
+    #[test]
+    fn rejects_networks_without_structure() {
+        assert_eq!(solve_part2("broadcaster -> a\n%a -> b".to_string()), Err(AnalysisError::NoRx));
+        assert_eq!(solve_part2("broadcaster -> a\n%a -> rx".to_string()), Err(AnalysisError::FeederNotConjunction("a".to_string())));
+        assert_eq!(
+            solve_part2("broadcaster -> a, b\n%a -> rx\n%b -> rx".to_string()),
+            Err(AnalysisError::MultipleFeeders(vec!["a".to_string(), "b".to_string()]))
+        );
+    }
+
+    #[test]
+    fn rejects_feeder_without_inputs() {
+        let content = "broadcaster -> a\n%a -> b\n&hub -> rx";
+        assert_eq!(solve_part2(content.to_string()), Err(AnalysisError::FeederWithoutInputs("hub".to_string())));
+        assert_eq!(press_until_rx_low(content.to_string(), 100), None);
+    }
+
+    #[test]
+    fn rejects_inputs_that_are_not_periodic() {
+        // a sends high on every odd press
+        let content = "broadcaster -> a\n%a -> hub\n&hub -> rx";
+        assert_eq!(solve_part2(content.to_string()), Err(AnalysisError::NotPeriodic { input: "a".to_string(), first: 1, second: 3 }));
+    }
+
+    #[test]
+    fn rejects_inputs_that_never_fire() {
+        // c only ever sees high from inv, so it never turns on
+        let content = "broadcaster -> a, inv\n%a -> hub\n&inv -> c\n%c -> hub\n&hub -> rx";
+        assert_eq!(analyse_rx(content.to_string(), 1000), Err(AnalysisError::BudgetExceeded { inputs: vec!["c".to_string()], presses: 1000 }));
+    }
+}
+
+
+#[cfg(test)]
+mod dot_tests {
+    use crate::*;
+
+    #[test]
+    fn dot_has_typed_nodes_sinks_and_ordered_edges() {
+        let dot = network_to_dot("broadcaster -> b, a\n%a -> con\n%b -> con\n&con -> rx");
+
+        assert!(dot.starts_with("digraph network {"));
+        assert!(dot.contains("\"broadcast\" [label=\"broadcast\" shape=house"));
+        assert!(dot.contains("\"a\" [label=\"%a\" shape=box"));
+        assert!(dot.contains("\"con\" [label=\"&con\" shape=diamond"));
+        assert!(dot.contains("\"rx\" [shape=doublecircle"));
+
+        let to_b = dot.find("\"broadcast\" -> \"b\"").unwrap();
+        let to_a = dot.find("\"broadcast\" -> \"a\"").unwrap();
+        assert!(to_b < to_a);
+        assert_eq!(dot.matches(" -> ").count(), 6);
+    }
+}
+
+
+#[cfg(test)]
+mod repl_tests {
+    use crate::*;
+
+    const COUNTERS: &str = "broadcaster -> a, b
+%a -> ca
+&ca -> hub
+%b -> b2
+%b2 -> cb
+&cb -> hub
+&hub -> rx";
+
+    #[test]
+    fn step_delivers_one_signal_at_a_time() {
+        let mut simulator = Simulator::new(COUNTERS.to_string());
+
+        assert_eq!(simulator.execute("step"), "press 1: button -low-> broadcast");
+        assert_eq!(simulator.execute("queue"), "broadcast -low-> a\nbroadcast -low-> b");
+        assert_eq!(simulator.execute("step 2"), "press 1: broadcast -low-> a\npress 1: broadcast -low-> b");
+        assert_eq!(simulator.execute("show a"), "FlipFlopModule { name: \"a\", receivers: [\"ca\"], state: true }");
+    }
+
+    #[test]
+    fn press_stops_at_breakpoints() {
+        let mut simulator = Simulator::new(COUNTERS.to_string());
+
+        assert_eq!(simulator.execute("break rx receives low"), "breakpoint 1: rx receives low");
+        assert_eq!(simulator.execute("press 100"), "breakpoint 1 (rx receives low) at press 4: hub -low-> rx");
+
+        // the rest of press 4 is finished before pressing again
+        simulator.execute("delete 1");
+        assert_eq!(simulator.execute("press 1"), "pressed 5 times");
+        assert_eq!(simulator.execute("queue"), "queue is empty");
+    }
+
+    #[test]
+    fn press_without_breakpoints_counts_pulses() {
+        let mut simulator = Simulator::new("broadcaster -> a, b, c\n%a -> b\n%b -> c\n%c -> inv\n&inv -> a".to_string());
+
+        assert_eq!(simulator.execute("press 1000"), "pressed 1000 times");
+        assert_eq!(simulator.execute("status"), "presses: 1000, low: 8000, high: 4000, queued: 0");
+    }
+
+    #[test]
+    fn bad_commands_print_usage() {
+        let mut simulator = Simulator::new(COUNTERS.to_string());
+
+        assert_eq!(simulator.execute("break a sends"), "usage: break <module> sends|receives high|low");
+        assert_eq!(simulator.execute("press lots"), "usage: press [n]");
+        assert_eq!(simulator.execute("show nope"), "usage: show <module>");
+        assert_eq!(simulator.execute("delete 3"), "usage: delete <n>");
+    }
+}
+
+
+#[cfg(test)]
+mod snapshot_tests {
+    use crate::*;
+
+    const EXAMPLE: &str = "broadcaster -> a
+%a -> inv, con
+&inv -> b
+%b -> con
+&con -> output";
+
+    #[test]
+    fn snapshot_round_trips_through_text() {
+        let mut modules = create_modules(EXAMPLE.to_string());
+        press_button(&mut modules, |_| {});
+
+        let snapshot = Snapshot::take(&modules);
+        let text = snapshot.to_string();
+        assert_eq!(text, "%a on\n%b on\n&con a=high b=high\n&inv a=high\n");
+        assert_eq!(Snapshot::parse(&text), Ok(snapshot));
+    }
+
+    #[test]
+    fn restore_continues_from_snapshot() {
+        let mut modules = create_modules(EXAMPLE.to_string());
+        press_button(&mut modules, |_| {});
+        let after_one = Snapshot::take(&modules);
+        press_button(&mut modules, |_| {});
+        let after_two = Snapshot::take(&modules);
+
+        let mut restored = create_modules(EXAMPLE.to_string());
+        after_one.restore(&mut restored).unwrap();
+        assert_eq!(Snapshot::take(&restored), after_one);
+        press_button(&mut restored, |_| {});
+        assert_eq!(Snapshot::take(&restored), after_two);
+    }
+
+    #[test]
+    fn restore_rejects_mismatched_state() {
+        let mut modules = create_modules(EXAMPLE.to_string());
+
+        assert_eq!(Snapshot::parse("%x on").unwrap().restore(&mut modules), Err("unknown module x".to_string()));
+        assert!(Snapshot::parse("&a b=high").unwrap().restore(&mut modules).is_err());
+        assert!(Snapshot::parse("&con a=high").unwrap().restore(&mut modules).is_err());
+        assert_eq!(Snapshot::parse("%a maybe"), Err("line 1: expected on or off in \"%a maybe\"".to_string()));
+    }
+
+    #[test]
+    fn diff_lists_changed_modules() {
+        let first = Snapshot::parse("%a on\n%b off\n&con a=high b=low").unwrap();
+        let second = Snapshot::parse("%a on\n%b on\n&con a=low b=low\n%c off").unwrap();
+
+        assert_eq!(first.diff(&second), vec!["%b: off -> on", "c: only in second", "&con.a: high -> low"]);
+        assert!(first.diff(&first).is_empty());
+    }
+}
+
+
+#[cfg(test)]
+mod compiled_tests {
+    use crate::*;
+
+    const EXAMPLES: [&str; 3] = [
+        "broadcaster -> a, b, c\n%a -> b\n%b -> c\n%c -> inv\n&inv -> a",
+        "broadcaster -> a\n%a -> inv, con\n&inv -> b\n%b -> con\n&con -> output",
+        "broadcaster -> a, b\n%a -> ca\n&ca -> hub\n%b -> b2\n%b2 -> cb\n&cb -> hub\n&hub -> rx",
+    ];
+
+    #[test]
+    fn compiled_network_sends_the_same_signals() {
+        for example in EXAMPLES {
+            let mut modules = create_modules(example.to_string());
+            let mut network = CompiledNetwork::compile(example);
+
+            for _ in 0..100 {
+                let mut expected = vec![];
+                press_button(&mut modules, |signal| expected.push(signal.clone()));
+
+                let mut signals = vec![];
+                network.press(|signal| signals.push(*signal));
+                let signals = signals
+                    .iter()
+                    .map(|signal| Signal { pulse: signal.pulse, sender: network.name(signal.sender).to_string(), receiver: network.name(signal.receiver).to_string() })
+                    .collect::<Vec<Signal>>();
+
+                assert_eq!(signals, expected);
+            }
+        }
+    }
+
+    #[test]
+    fn duplicate_definitions_keep_the_last_one() {
+        let content = "broadcaster -> a, b\n%a -> con\n%b -> con\n%a -> b\n&con -> rx";
+        let mut modules = create_modules(content.to_string());
+        let mut network = CompiledNetwork::compile(content);
+
+        for _ in 0..10 {
+            let mut expected = vec![];
+            press_button(&mut modules, |signal| expected.push(signal.clone()));
+
+            let mut signals = vec![];
+            network.press(|signal| signals.push(*signal));
+            let signals = signals
+                .iter()
+                .map(|signal| Signal { pulse: signal.pulse, sender: network.name(signal.sender).to_string(), receiver: network.name(signal.receiver).to_string() })
+                .collect::<Vec<Signal>>();
+
+            assert_eq!(signals, expected);
+        }
+
+        let con = network.id("con").unwrap() as usize;
+        assert!(matches!(network.modules[con], CompiledModule::Conjunction { inputs: 1, .. }));
+    }
+
+    #[test]
+    fn conjunction_with_many_inputs_uses_several_words() {
+        let inputs = (0..100).map(|n| format!("f{}", n)).collect::<Vec<String>>();
+        let mut content = format!("broadcaster -> {}\n", inputs.join(", "));
+        for input in inputs.iter() {
+            content.push_str(&format!("%{} -> con\n", input));
+        }
+        content.push_str("&con -> rx");
+
+        let mut network = CompiledNetwork::compile(&content);
+        let con = network.id("con").unwrap() as usize;
+        assert_eq!(network.modules[con], CompiledModule::Conjunction { memory: vec![0, 0], inputs: 100, high: 0 });
+
+        // every flip-flop turns on, so the last signal to con completes it
+        let rx = network.id("rx").unwrap();
+        let mut to_rx = vec![];
+        network.press(|signal| if signal.receiver == rx { to_rx.push(signal.pulse) });
+        assert_eq!(to_rx.len(), 100);
+        assert_eq!(to_rx.iter().filter(|pulse| !**pulse).count(), 1);
+        assert!(!to_rx.last().unwrap());
+    }
+}
+
+
+#[cfg(test)]
+mod cycle_tests {
+    use crate::*;
+
+    const FIRST: &str = "broadcaster -> a, b, c\n%a -> b\n%b -> c\n%c -> inv\n&inv -> a";
+    const SECOND: &str = "broadcaster -> a\n%a -> inv, con\n&inv -> b\n%b -> con\n&con -> output";
+
+    #[test]
+    fn examples_after_1000_presses() {
+        assert_eq!(solve_part1(FIRST.to_string(), 1000), 32000000);
+        assert_eq!(solve_part1(SECOND.to_string(), 1000), 11687500);
+    }
+
+    #[test]
+    fn extrapolation_matches_direct_simulation() {
+        for content in [FIRST, SECOND] {
+            for presses in 0..40 {
+                let extrapolated = count_pulses(content, presses, CYCLE_PRESS_BUDGET);
+                let direct = count_pulses(content, presses, 0);
+                assert_eq!((extrapolated.low, extrapolated.high), (direct.low, direct.high));
+                assert_eq!(direct.cycle, None);
+            }
+        }
+    }
+
+    #[test]
+    fn huge_press_counts() {
+        let counts = count_pulses(FIRST, 1_000_000_000_000, CYCLE_PRESS_BUDGET);
+        assert_eq!(counts, PulseCounts { low: 8_000_000_000_000, high: 4_000_000_000_000, cycle: Some((0, 1)) });
+
+        let counts = count_pulses(SECOND, 10u128.pow(15) + 2, CYCLE_PRESS_BUDGET);
+        assert_eq!(counts.cycle, Some((0, 4)));
+        // 17 low and 11 high per cycle of 4 presses, plus 4 low and 4 high for the first two presses of the next one
+        assert_eq!((counts.low, counts.high), (17 * 250_000_000_000_000 + 8, 11 * 250_000_000_000_000 + 6));
+    }
+}
+
+
+#[cfg(test)]
+mod gate_tests {
+    use crate::*;
+
+    fn signal(sender: &str, receiver: &str, pulse: bool) -> Signal {
+        return Signal { pulse, sender: sender.to_string(), receiver: receiver.to_string() };
+    }
+
+    #[test]
+    fn inverter_flips_pulses() {
+        let mut inverter = InverterModule { name: "not".to_string(), receivers: vec!["a".to_string()] };
+        assert_eq!(inverter.receive(signal("x", "not", true)), vec![signal("not", "a", false)]);
+        assert_eq!(inverter.receive(signal("x", "not", false)), vec![signal("not", "a", true)]);
+    }
+
+    #[test]
+    fn or_gate_sends_high_when_any_input_is_high() {
+        let mut or = OrModule { name: "or".to_string(), receivers: vec!["a".to_string()], state: HashMap::new() };
+        or.add_sender("x".to_string());
+        or.add_sender("y".to_string());
+
+        assert_eq!(or.receive(signal("x", "or", false)), vec![signal("or", "a", false)]);
+        assert_eq!(or.receive(signal("y", "or", true)), vec![signal("or", "a", true)]);
+        assert_eq!(or.receive(signal("x", "or", true)), vec![signal("or", "a", true)]);
+        assert_eq!(or.receive(signal("y", "or", false)), vec![signal("or", "a", true)]);
+        assert_eq!(or.receive(signal("x", "or", false)), vec![signal("or", "a", false)]);
+    }
+
+    #[test]
+    fn counter_passes_pulses_on_and_counts_them() {
+        let mut counter = CounterModule { name: "c".to_string(), receivers: vec!["a".to_string()], low: 0, high: 0 };
+        assert_eq!(counter.receive(signal("x", "c", true)), vec![signal("c", "a", true)]);
+        counter.receive(signal("x", "c", false));
+        counter.receive(signal("x", "c", false));
+        assert_eq!(counter.state(), ModuleState::Counter { low: 2, high: 1 });
+    }
+
+    #[test]
+    fn delay_sends_previous_pulse() {
+        let mut delay = DelayModule { name: "d".to_string(), receivers: vec!["a".to_string()], state: false };
+        assert_eq!(delay.receive(signal("x", "d", true)), vec![signal("d", "a", false)]);
+        assert_eq!(delay.receive(signal("x", "d", true)), vec![signal("d", "a", true)]);
+        assert_eq!(delay.receive(signal("x", "d", false)), vec![signal("d", "a", true)]);
+        assert_eq!(delay.receive(signal("x", "d", false)), vec![signal("d", "a", false)]);
+    }
+
+    #[test]
+    fn probe_records_what_it_receives() {
+        let mut modules = create_modules("broadcaster -> a, p\n%a -> p\n?p -> out".to_string());
+        press_button(&mut modules, |_| {});
+        press_button(&mut modules, |_| {});
+        assert_eq!(
+            modules["p"].state(),
+            ModuleState::Probe(vec![("broadcast".to_string(), false), ("a".to_string(), true), ("broadcast".to_string(), false), ("a".to_string(), false)])
+        );
+    }
+
+    #[test]
+    fn registry_accepts_new_prefixes() {
+        let content = "broadcaster -> a\n@a -> out";
+        assert_eq!(
+            ModuleRegistry::standard().create_modules(content).err(),
+            Some("unknown module prefix '@' in \"@a -> out\"".to_string())
+        );
+
+        let mut registry = ModuleRegistry::standard();
+        registry.register('@', |name, receivers| Box::new(InverterModule { name, receivers }));
+        let mut modules = registry.create_modules(content).unwrap();
+
+        let mut pulses = vec![];
+        press_button(&mut modules, |signal| pulses.push(signal.to_string()));
+        assert_eq!(pulses, vec!["button -low-> broadcast", "broadcast -low-> a", "a -high-> out"]);
+    }
+
+    // one network per gate, and one with all of them where the delays keep pulses between presses
+    const GATE_NETWORKS: [&str; 6] = [
+        "broadcaster -> a\n%a -> n\n!n -> rx",
+        "broadcaster -> a, b\n%a -> o\n%b -> b2\n%b2 -> o\n|o -> rx",
+        "broadcaster -> a\n%a -> c, b\n%b -> c\n#c -> rx",
+        "broadcaster -> a\n%a -> d\n~d -> b\n%b -> rx",
+        "broadcaster -> a, p\n%a -> p\n?p -> rx",
+        "broadcaster -> a, d\n~d -> a\n%a -> o, d2\n~d2 -> o, b\n%b -> o\n|o -> n\n!n -> c\n#c -> p\n?p -> rx",
+    ];
+
+    #[test]
+    fn compiled_engines_run_every_gate() {
+        for content in GATE_NETWORKS {
+            let mut modules = create_modules(content.to_string());
+            let (mut low, mut high) = (0, 0);
+            for _ in 0..1000 {
+                press_button(&mut modules, |signal| if signal.pulse { high += 1 } else { low += 1 });
+            }
+            assert_eq!(solve_part1(content.to_string(), 1000), low * high, "{}", content);
+            assert_eq!(count_pulses(content, 1000, 0), PulseCounts { low, high, cycle: None }, "{}", content);
+
+            let mut parallel = ParallelNetwork::new(content, 1);
+            for _ in 0..1000 {
+                parallel.press(|_| {});
+            }
+            assert_eq!((parallel.low[0], parallel.high[0]), (low, high), "{}", content);
+        }
+    }
+
+    #[test]
+    fn compiled_gates_send_the_same_signals() {
+        for content in GATE_NETWORKS {
+            let mut modules = create_modules(content.to_string());
+            let mut network = CompiledNetwork::compile(content);
+            let names = network.names.clone();
+
+            for _ in 0..20 {
+                let mut expected = vec![];
+                press_button(&mut modules, |signal| expected.push(signal.to_string()));
+                let mut signals = vec![];
+                network.press(|signal| signals.push(format!("{} -{}-> {}", names[signal.sender as usize], pulse_name(signal.pulse), names[signal.receiver as usize])));
+                assert_eq!(signals, expected, "{}", content);
+            }
+        }
+    }
+
+    #[test]
+    fn lanes_start_from_gate_states() {
+        let content = GATE_NETWORKS[5];
+        let mut modules = create_modules(content.to_string());
+        for _ in 0..3 {
+            press_button(&mut modules, |_| {});
+        }
+        let snapshot = Snapshot::take(&modules);
+
+        let mut parallel = ParallelNetwork::new(content, 2);
+        parallel.set_lane(1, &snapshot).unwrap();
+        assert_eq!(parallel.lane_snapshot(1).states["d2"], snapshot.states["d2"]);
+        assert_eq!(parallel.lane_snapshot(1).states["o"], snapshot.states["o"]);
+
+        for _ in 0..10 {
+            parallel.press(|_| {});
+            press_button(&mut modules, |_| {});
+        }
+        let expected = Snapshot::take(&modules);
+        for (name, state) in parallel.lane_snapshot(1).states {
+            assert_eq!(expected.states[&name], state, "{}", name);
+        }
+    }
+
+    #[test]
+    fn new_gate_states_round_trip_through_snapshots() {
+        let text = "#c low=2 high=1\n~d high\n|o x=high y=low\n?p b=low a=high\n";
+        let snapshot = Snapshot::parse(text).unwrap();
+        assert_eq!(snapshot.states["p"], ModuleState::Probe(vec![("b".to_string(), false), ("a".to_string(), true)]));
+        assert_eq!(snapshot.to_string(), text);
+    }
+}
+
+
+#[cfg(test)]
+mod validation_tests {
+    use crate::*;
+
+    fn validate(content: &str) -> Vec<Finding> {
+        return validate_network(content, &ModuleRegistry::standard(), &KNOWN_SINKS);
+    }
+
+    #[test]
+    fn examples_are_valid() {
+        assert_eq!(validate("broadcaster -> a, b, c\n%a -> b\n%b -> c\n%c -> inv\n&inv -> a"), vec![]);
+
+        let findings = validate("broadcaster -> a\n%a -> inv, con\n&inv -> b\n%b -> con\n&con -> output");
+        assert_eq!(findings, vec![Finding::Sink { lines: vec![5], name: "output".to_string() }]);
+        assert!(!findings[0].is_error());
+    }
+
+    #[test]
+    fn reports_mistakes_with_line_numbers() {
+        let content = "broadcaster -> a, b
+%a -> con, xq
+%b -> con
+&con -> rx
+%a -> b
+broadcastr -> a
+&lonely -> a
+a -> b
+%c -> rx
+@d -> a";
+        let findings = validate(content);
+
+        assert_eq!(findings, vec![
+            Finding::Duplicate { lines: vec![1, 6, 8], name: "broadcast".to_string() },
+            Finding::Duplicate { lines: vec![2, 5], name: "a".to_string() },
+            Finding::UndefinedReceiver { line: 2, sender: "a".to_string(), receiver: "xq".to_string() },
+            Finding::Sink { lines: vec![4, 9], name: "rx".to_string() },
+            Finding::RenamedBroadcaster { line: 6, name: "broadcastr".to_string() },
+            Finding::NoInputs { line: 7, name: "lonely".to_string() },
+            Finding::Unreachable { line: 7, name: "lonely".to_string() },
+            Finding::RenamedBroadcaster { line: 8, name: "a".to_string() },
+            Finding::Unreachable { line: 9, name: "c".to_string() },
+            Finding::UnknownPrefix { line: 10, prefix: '@' },
+            Finding::Unreachable { line: 10, name: "d".to_string() },
+        ]);
+        assert_eq!(findings[1].to_string(), "lines 2, 5: a is defined more than once");
+    }
+
+    #[test]
+    fn gates_the_solver_cannot_run_are_errors() {
+        assert_eq!(validate("broadcaster -> a\n%a -> n\n!n -> rx"), vec![Finding::Sink { lines: vec![3], name: "rx".to_string() }]);
+
+        let mut registry = ModuleRegistry::standard();
+        registry.register('@', |name, receivers| Box::new(InverterModule { name, receivers }));
+        let findings = validate_network("broadcaster -> a\n@a -> rx", &registry, &KNOWN_SINKS);
+        assert_eq!(findings[0], Finding::Unsupported { line: 2, prefix: '@' });
+        assert!(findings[0].is_error());
+        assert_eq!(findings[0].to_string(), "line 2: '@' modules are not supported by the solver");
+    }
+
+    #[test]
+    fn reports_missing_broadcast_and_malformed_lines() {
+        let findings = validate("%a -> b\n\n&b ->");
+        assert_eq!(findings, vec![
+            Finding::MissingBroadcast,
+            Finding::UndefinedReceiver { line: 1, sender: "a".to_string(), receiver: "b".to_string() },
+            Finding::Malformed { line: 2 },
+            Finding::Malformed { line: 3 },
+        ]);
+    }
+}
+
+
+#[cfg(test)]
+mod trace_tests {
+    use crate::*;
+
+    const EXAMPLE: &str = "broadcaster -> a\n%a -> inv, con\n&inv -> b\n%b -> con\n&con -> output";
+
+    fn trace(presses: usize, filter: &TraceFilter) -> (String, BTreeMap<String, ModuleCounts>) {
+        let mut out = vec![];
+        let summary = record_trace(EXAMPLE, presses, filter, &mut out).unwrap();
+        return (String::from_utf8(out).unwrap(), summary);
+    }
+
+    #[test]
+    fn trace_lists_every_pulse_in_order() {
+        let (text, summary) = trace(1, &TraceFilter::default());
+        assert_eq!(text, "press,seq,sender,receiver,level
+1,0,button,broadcast,low
+1,1,broadcast,a,low
+1,2,a,inv,high
+1,3,a,con,high
+1,4,inv,b,low
+1,5,con,output,high
+1,6,b,con,high
+1,7,con,output,low
+");
+        assert_eq!(summary["con"], ModuleCounts { sent_low: 1, sent_high: 1, received_low: 0, received_high: 2 });
+    }
+
+    #[test]
+    fn trace_filters_keep_sequence_numbers() {
+        let filter = TraceFilter { modules: vec!["output".to_string()], level: Some(Level::Low) };
+        let (text, summary) = trace(4, &filter);
+        assert_eq!(text, "press,seq,sender,receiver,level\n1,7,con,output,low\n3,5,con,output,low\n");
+        assert_eq!(summary.keys().collect::<Vec<&String>>(), vec!["con", "output"]);
+        assert_eq!(summary["output"].received_low, 2);
+    }
+
+    #[test]
+    fn trace_summary_totals_match_pulse_counts() {
+        let (_, summary) = trace(1000, &TraceFilter::default());
+        let low: usize = summary.values().map(|counts| counts.received_low).sum();
+        let high: usize = summary.values().map(|counts| counts.sent_high).sum();
+        assert_eq!((low, high), (4250, 2750));
+    }
+}
+
+
+#[cfg(test)]
+mod counter_tests {
+    use crate::*;
+
+    // the shape of the puzzle inputs: each counter's conjunction is fed by the flip-flops
+    // for the bits of its period, and sends low to the others and to bit 0 to reset
+    fn counter_network(bits: usize, periods: &[u128]) -> String {
+        let mut lines = vec![];
+        let mut heads = vec![];
+        for (c, period) in periods.iter().enumerate() {
+            let flip_flops = (0..bits).map(|k| format!("f{}x{}", c, k)).collect::<Vec<String>>();
+            heads.push(flip_flops[0].clone());
+
+            let mut resets = vec![];
+            for k in 0..bits {
+                let mut receivers = vec![];
+                if k + 1 < bits {
+                    receivers.push(flip_flops[k + 1].clone());
+                }
+                if period >> k & 1 == 1 {
+                    receivers.push(format!("c{}", c));
+                }
+                if period >> k & 1 == 0 || k == 0 {
+                    resets.push(flip_flops[k].clone());
+                }
+                lines.push(format!("%{} -> {}", flip_flops[k], receivers.join(", ")));
+            }
+            resets.push(format!("i{}", c));
+            lines.push(format!("&c{} -> {}", c, resets.join(", ")));
+            lines.push(format!("&i{} -> hub", c));
+        }
+        lines.insert(0, format!("broadcaster -> {}", heads.join(", ")));
+        lines.push("&hub -> rx".to_string());
+        return lines.join("\n");
+    }
+
+    #[test]
+    fn finds_counters_and_their_periods() {
+        let content = counter_network(4, &[5, 7, 11]);
+        let counters = find_counters(&content).unwrap();
+
+        assert_eq!(counters.iter().map(|counter| (counter.period, counter.reset, counter.cycle())).collect::<Vec<_>>(), vec![(5, 0, 5), (7, 0, 7), (11, 0, 11)]);
+        assert_eq!(counters[0].flip_flops, vec!["f0x0", "f0x1", "f0x2", "f0x3"]);
+        assert_eq!((counters[0].conjunction.as_str(), counters[0].inverter.as_str()), ("c0", "i0"));
+
+        assert_eq!(predict_rx(&counters), Ok(385));
+        assert_eq!(analyse_rx(content.clone(), 1000), Ok(385));
+        assert_eq!(cross_check_counters(&content, &counters, CROSS_CHECK_PRESS_BUDGET), (22, vec![]));
+    }
+
+    #[test]
+    fn puzzle_sized_counters() {
+        let content = counter_network(12, &[3769, 3797, 3863, 4001]);
+        let counters = find_counters(&content).unwrap();
+
+        assert_eq!(predict_rx(&counters), Ok(3769 * 3797 * 3863 * 4001));
+        assert_eq!(cross_check_counters(&content, &counters, CROSS_CHECK_PRESS_BUDGET).1, Vec::<String>::new());
+    }
+
+    #[test]
+    fn counters_with_a_nonzero_reset() {
+        // all three bits feed c0, which only turns bit 2 off again: 7 -> 3 -> ... -> 7
+        let content = "broadcaster -> a0, b0
+%a0 -> a1, c0
+%a1 -> a2, c0
+%a2 -> c0
+&c0 -> a2, i0
+&i0 -> hub
+%b0 -> b1, c1
+%b1 -> b2
+%b2 -> c1
+&c1 -> b0, b1, i1
+&i1 -> hub
+&hub -> rx";
+        let counters = find_counters(content).unwrap();
+
+        assert_eq!(counters.iter().map(|counter| (counter.period, counter.reset, counter.cycle())).collect::<Vec<_>>(), vec![(7, 3, 4), (5, 0, 5)]);
+        assert_eq!(predict_rx(&counters), Ok(15));
+        assert_eq!(cross_check_counters(content, &counters, CROSS_CHECK_PRESS_BUDGET).1, Vec::<String>::new());
+
+        let mut modules = create_modules(content.to_string());
+        let mut rx_low = vec![];
+        for press in 1..=15 {
+            press_button(&mut modules, |signal| if signal.receiver == "rx" && !signal.pulse { rx_low.push(press) });
+        }
+        assert_eq!(rx_low, vec![15]);
+    }
+
+    #[test]
+    fn rejects_other_structures() {
+        assert_eq!(find_counters("broadcaster -> a\n&a -> rx"), Err(StructureError::NotAFlipFlop("a".to_string())));
+        assert_eq!(find_counters("broadcaster -> a\n%a -> b, c\n%b -> rx\n%c -> rx"), Err(StructureError::BranchingChain("a".to_string())));
+        assert_eq!(find_counters("broadcaster -> a\n%a -> rx"), Err(StructureError::NoConjunction("a".to_string())));
+        assert_eq!(
+            find_counters("broadcaster -> a\n%a -> c\n&c -> a, x, y\n%x -> rx\n%y -> rx"),
+            Err(StructureError::UnexpectedOutputs { conjunction: "c".to_string(), outputs: vec!["x".to_string(), "y".to_string()] })
+        );
+    }
+
+    #[test]
+    fn reset_check_matches_counting_up() {
+        for bits in 1..=6 {
+            let size = 1u128 << bits;
+            for period in 1..size {
+                for reset in 0..size {
+                    let mut value = reset;
+                    loop {
+                        value = (value + 1) % size;
+                        if value & period == period {
+                            break;
+                        }
+                    }
+                    assert_eq!(fires_at_period(period, reset, size), value == period, "period {} reset {}", period, reset);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn long_chains() {
+        let content = counter_network(127, &[(1 << 126) + 1]);
+        let counters = find_counters(&content).unwrap();
+        assert_eq!((counters[0].period, counters[0].reset, counters[0].cycle()), ((1 << 126) + 1, 0, (1 << 126) + 1));
+
+        let content = counter_network(128, &[(1 << 127) + 1]);
+        assert_eq!(find_counters(&content), Err(StructureError::ChainTooLong { head: "f0x0".to_string(), length: 128 }));
+    }
+
+    #[test]
+    fn several_long_chains() {
+        let periods = [(1 << 60) + 1, (1 << 60) + 3, (1 << 59) + 5];
+        let content = counter_network(100, &periods);
+        let counters = find_counters(&content).unwrap();
+        assert_eq!(counters.iter().map(|counter| counter.cycle()).collect::<Vec<u128>>(), periods);
+        assert_eq!(predict_rx(&counters[..2]), Ok(periods[0] * periods[1]));
+
+        let content = counter_network(100, &[(1 << 99) + 1, (1 << 99) + 3]);
+        let counters = find_counters(&content).unwrap();
+        assert_eq!(predict_rx(&counters), Err(StructureError::TooManyPresses));
+
+        let content = counter_network(127, &[(1 << 126) + 1, u128::MAX >> 1]);
+        let counters = find_counters(&content).unwrap();
+        assert_eq!(predict_rx(&counters), Err(StructureError::TooManyPresses));
+    }
+
+    #[test]
+    fn congruences() {
+        assert_eq!(combine_congruences((0, 4), (0, 6)), Some((0, 12)));
+        assert_eq!(combine_congruences((3, 4), (0, 5)), Some((15, 20)));
+        assert_eq!(combine_congruences((1, 4), (0, 6)), None);
+    }
+}
+
+
+#[cfg(test)]
+mod parallel_tests {
+    use crate::*;
+    use crate::fixtures::xorshift;
+
+    const EXAMPLES: [&str; 3] = [
+        "broadcaster -> a, b, c\n%a -> b\n%b -> c\n%c -> inv\n&inv -> a",
+        "broadcaster -> a\n%a -> inv, con\n&inv -> b\n%b -> con\n&con -> output",
+        "broadcaster -> a0, b0\n%a0 -> a1, c0\n%a1 -> a2, c0\n%a2 -> c0\n&c0 -> a2, i0\n&i0 -> hub\n%b0 -> b1, c1\n%b1 -> b2\n%b2 -> c1\n&c1 -> b0, b1, i1\n&i1 -> hub\n&hub -> rx",
+    ];
+
+    // arbitrary states can make a press loop forever, so start every lane from a state the
+    // network reaches by itself
+    fn random_snapshot(content: &str, seed: &mut u64) -> Snapshot {
+        let mut modules = create_modules(content.to_string());
+        for _ in 0..xorshift(seed) % 200 {
+            press_button(&mut modules, |_| {});
+        }
+        return Snapshot::take(&modules);
+    }
+
+    #[test]
+    fn lanes_match_scalar_modules() {
+        let mut seed = 0x2545f4914f6cdd1d;
+
+        for content in EXAMPLES {
+            let mut parallel = ParallelNetwork::new(content, LANES);
+            let mut scalar = vec![];
+            for lane in 0..LANES {
+                let mut modules = create_modules(content.to_string());
+                let snapshot = random_snapshot(content, &mut seed);
+                snapshot.restore(&mut modules).unwrap();
+                parallel.set_lane(lane, &snapshot).unwrap();
+                assert_eq!(parallel.lane_snapshot(lane), snapshot);
+                scalar.push(modules);
+            }
+            let names = parallel.network.names.clone();
+
+            for _ in 0..50 {
+                let mut lane_signals: Vec<Vec<String>> = vec![vec![]; LANES];
+                parallel.press(|signal| {
+                    for (lane, signals) in lane_signals.iter_mut().enumerate().filter(|(lane, _)| signal.active >> lane & 1 == 1) {
+                        let pulse = if signal.pulse >> lane & 1 == 1 { "high" } else { "low" };
+                        signals.push(format!("{} -{}-> {}", names[signal.sender as usize], pulse, names[signal.receiver as usize]));
+                    }
+                });
+
+                for (lane, modules) in scalar.iter_mut().enumerate() {
+                    let mut expected = vec![];
+                    press_button(modules, |signal| expected.push(signal.to_string()));
+                    assert_eq!(lane_signals[lane], expected);
+                    assert_eq!(parallel.lane_snapshot(lane), Snapshot::take(modules));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn lanes_count_their_own_pulses() {
+        let content = EXAMPLES[1];
+        let mut parallel = ParallelNetwork::new(content, 2);
+
+        // lane 1 starts one press ahead of lane 0
+        let mut modules = create_modules(content.to_string());
+        press_button(&mut modules, |_| {});
+        parallel.set_lane(1, &Snapshot::take(&modules)).unwrap();
+
+        for _ in 0..1000 {
+            parallel.press(|_| {});
+        }
+        assert_eq!((parallel.low[0], parallel.high[0]), (4250, 2750));
+        assert_eq!(count_pulses(content, 1001, 0).low - 4, parallel.low[1]);
+        assert_eq!(count_pulses(content, 1001, 0).high - 4, parallel.high[1]);
+    }
+
+    #[test]
+    fn set_lane_rejects_unknown_modules() {
+        let mut parallel = ParallelNetwork::new(EXAMPLES[1], 1);
+        assert_eq!(parallel.set_lane(0, &Snapshot::parse("%x on").unwrap()), Err("unknown module x".to_string()));
+        assert!(parallel.set_lane(0, &Snapshot::parse("&con a=high").unwrap()).is_err());
+        assert!(parallel.set_lane(0, &Snapshot::parse("&a b=high").unwrap()).is_err());
+    }
+}
+
+#[cfg(test)]
+mod scenario_tests {
+    use crate::*;
+    use crate::fixtures::*;
+
+    fn pulses(content: &str, presses: usize) -> Vec<Vec<String>> {
+        let mut modules = create_modules(content.to_string());
+        let mut result = vec![];
+        for _ in 0..presses {
+            let mut signals = vec![];
+            press_button(&mut modules, |signal| signals.push(signal.to_string()));
+            result.push(signals);
+        }
+        return result;
+    }
+
+    fn counts(content: &str, presses: usize) -> (u128, u128) {
+        let mut modules = create_modules(content.to_string());
+        let (mut low, mut high) = (0, 0);
+        for _ in 0..presses {
+            press_button(&mut modules, |signal| if signal.pulse { high += 1 } else { low += 1 });
+        }
+        return (low, high);
+    }
+
+    #[test]
+    fn first_example_pulse_order() {
+        assert_eq!(pulses(FIRST_EXAMPLE, 1)[0], vec![
+            "button -low-> broadcast",
+            "broadcast -low-> a",
+            "broadcast -low-> b",
+            "broadcast -low-> c",
+            "a -high-> b",
+            "b -high-> c",
+            "c -high-> inv",
+            "inv -low-> a",
+            "a -low-> b",
+            "b -low-> c",
+            "c -low-> inv",
+            "inv -high-> a",
+        ]);
+    }
+
+    #[test]
+    fn second_example_pulse_order() {
+        let presses = pulses(SECOND_EXAMPLE, 4);
+
+        assert_eq!(presses[0], vec![
+            "button -low-> broadcast",
+            "broadcast -low-> a",
+            "a -high-> inv",
+            "a -high-> con",
+            "inv -low-> b",
+            "con -high-> output",
+            "b -high-> con",
+            "con -low-> output",
+        ]);
+        assert_eq!(presses[1], vec![
+            "button -low-> broadcast",
+            "broadcast -low-> a",
+            "a -low-> inv",
+            "a -low-> con",
+            "inv -high-> b",
+            "con -high-> output",
+        ]);
+        assert_eq!(presses[2], vec![
+            "button -low-> broadcast",
+            "broadcast -low-> a",
+            "a -high-> inv",
+            "a -high-> con",
+            "inv -low-> b",
+            "con -low-> output",
+            "b -low-> con",
+            "con -high-> output",
+        ]);
+        assert_eq!(presses[3], presses[1]);
+    }
+
+    #[test]
+    fn counts_after_presses() {
+        for (content, expected) in [
+            (FIRST_EXAMPLE, [(1, (8, 4)), (4, (32, 16)), (1000, (8000, 4000))]),
+            (SECOND_EXAMPLE, [(1, (4, 4)), (4, (17, 11)), (1000, (4250, 2750))]),
+        ] {
+            for (presses, (low, high)) in expected {
+                assert_eq!(counts(content, presses), (low, high), "{} presses of {:?}", presses, content);
+
+                let compiled = count_pulses(content, presses as u128, CYCLE_PRESS_BUDGET);
+                assert_eq!((compiled.low, compiled.high), (low, high), "{} presses of {:?}", presses, content);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod property_tests {
+    use crate::*;
+    use crate::fixtures::*;
+
+    // modules only send to modules defined after them, so every press settles
+    fn random_network(seed: &mut u64) -> String {
+        let count = 3 + xorshift(seed) as usize % 8;
+        let names = (0..count).map(|i| format!("m{}", i)).collect::<Vec<String>>();
+
+        let mut heads = names.iter().filter(|_| xorshift(seed).is_multiple_of(2)).cloned().collect::<Vec<String>>();
+        if heads.is_empty() {
+            heads.push(names[0].clone());
+        }
+
+        let mut lines = vec![format!("broadcaster -> {}", heads.join(", "))];
+        for (i, name) in names.iter().enumerate() {
+            let mut receivers = names[i + 1..].iter().filter(|_| xorshift(seed).is_multiple_of(3)).cloned().collect::<Vec<String>>();
+            if receivers.is_empty() || xorshift(seed).is_multiple_of(4) {
+                receivers.push("rx".to_string());
+            }
+            let prefix = if xorshift(seed).is_multiple_of(2) { '%' } else { '&' };
+            lines.push(format!("{}{} -> {}", prefix, name, receivers.join(", ")));
+        }
+        return lines.join("\n");
+    }
+
+    #[test]
+    fn pulses_per_edge_agree_across_engines() {
+        let mut seed = 0x9e3779b97f4a7c15;
+
+        for _ in 0..200 {
+            let content = random_network(&mut seed);
+            let mut modules = create_modules(content.clone());
+            let mut network = CompiledNetwork::compile(&content);
+            let names = network.names.clone();
+
+            // (low, high) sent along every edge by each engine
+            let mut scalar: HashMap<(String, String), (u128, u128)> = HashMap::new();
+            let mut compiled: HashMap<(String, String), (u128, u128)> = HashMap::new();
+            // pulses that make a module send: everything for conjunctions and the broadcaster,
+            // only low pulses for flip-flops
+            let mut triggers: HashMap<String, u128> = HashMap::new();
+            for _ in 0..20 {
+                press_button(&mut modules, |signal| {
+                    let edge = scalar.entry((signal.sender.clone(), signal.receiver.clone())).or_default();
+                    if signal.pulse { edge.1 += 1 } else { edge.0 += 1 }
+                    if !signal.pulse || !content.contains(&format!("%{} ", signal.receiver)) {
+                        *triggers.entry(signal.receiver.clone()).or_default() += 1;
+                    }
+                });
+                network.press(|signal| {
+                    let edge = compiled.entry((names[signal.sender as usize].clone(), names[signal.receiver as usize].clone())).or_default();
+                    if signal.pulse { edge.1 += 1 } else { edge.0 += 1 }
+                });
+            }
+
+            assert_eq!(scalar, compiled, "{}", content);
+            for line in content.lines() {
+                let name = parse_module_name(line);
+                let expected = triggers.get(&name).copied().unwrap_or(0);
+                for receiver in parse_receivers(line) {
+                    let (low, high) = scalar.get(&(name.clone(), receiver.clone())).copied().unwrap_or((0, 0));
+                    assert_eq!(low + high, expected, "{} -> {} in {}", name, receiver, content);
+                }
+            }
+
+            let counts = count_pulses(&content, 20, CYCLE_PRESS_BUDGET);
+            let low = scalar.values().map(|(low, _)| low).sum::<u128>();
+            let high = scalar.values().map(|(_, high)| high).sum::<u128>();
+            assert_eq!((counts.low, counts.high), (low, high), "{}", content);
+        }
+    }
+
+    #[test]
+    fn flip_flops_flip_exactly_on_low_pulses() {
+        let mut seed = 0xd1b54a32d192ed03;
+
+        for _ in 0..200 {
+            let content = random_network(&mut seed);
+            let mut modules = create_modules(content.clone());
+
+            for _ in 0..20 {
+                let mut queue = VecDeque::from([Signal { pulse: false, sender: "button".to_string(), receiver: "broadcast".to_string() }]);
+                while let Some(signal) = queue.pop_front() {
+                    let Some(module) = modules.get_mut(&signal.receiver) else { continue };
+                    let before = module.state();
+                    let pulse = signal.pulse;
+                    queue.extend(module.receive(signal));
+
+                    if let (ModuleState::FlipFlop(before), ModuleState::FlipFlop(after)) = (before, module.state()) {
+                        assert_eq!(before != after, !pulse, "{}", content);
+                    }
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,231 @@
+#[path = "../grid.rs"]
+mod grid;
+
+use clap::Parser;
+use grid::{Grid, GridIndex};
+use std::collections::BTreeMap;
+
+#[derive(Parser)]
+struct Cli {
+    path: std::path::PathBuf,
+
+    /// Print the symbols touching every number
+    #[arg(long)]
+    touching: bool,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+enum Cell {
+    #[default]
+    Empty,
+    Digit(u8),
+    Symbol(char),
+}
+
+// a number on one row, covering the columns start..end
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Span {
+    value: u64,
+    row: usize,
+    start: usize,
+    end: usize,
+}
+
+#[derive(Debug)]
+struct Schematic {
+    grid: Grid<Cell>,
+    numbers: Vec<Span>,
+    symbols: Vec<GridIndex>,
+}
+
+// which symbols touch which numbers, queryable from both sides
+#[derive(Debug)]
+struct Touching {
+    by_number: Vec<Vec<GridIndex>>,
+    by_symbol: BTreeMap<GridIndex, Vec<usize>>,
+}
+
+impl Touching {
+    fn symbols(&self, number: usize) -> &[GridIndex] {
+        return &self.by_number[number];
+    }
+
+    fn numbers(&self, symbol: GridIndex) -> &[usize] {
+        return self.by_symbol.get(&symbol).map(|numbers| numbers.as_slice()).unwrap_or(&[]);
+    }
+}
+
+impl Schematic {
+    fn parse(content: &str) -> Self {
+        let lines = content.lines().collect::<Vec<&str>>();
+        let cols = lines.iter().map(|line| line.chars().count()).max().unwrap_or(0);
+        let mut grid: Grid<Cell> = Grid::new(lines.len(), cols);
+
+        let mut numbers = vec![];
+        let mut symbols = vec![];
+        for (i, line) in lines.iter().enumerate() {
+            let mut current: Option<Span> = None;
+            for (j, c) in line.chars().enumerate() {
+                let index = grid.at(i, j);
+                let cell = match c {
+                    '.' => Cell::Empty,
+                    c if c.is_ascii_digit() => Cell::Digit(c as u8 - b'0'),
+                    c => Cell::Symbol(c),
+                };
+                *grid.get_mut(index).unwrap() = cell;
+
+                match cell {
+                    Cell::Digit(digit) => {
+                        let span = current.get_or_insert(Span { value: 0, row: i, start: j, end: j });
+                        span.value = span.value.checked_mul(10)
+                            .and_then(|value| value.checked_add(digit as u64))
+                            .unwrap_or_else(|| panic!("number at line {}, column {} does not fit in 64 bits", i + 1, span.start + 1));
+                        span.end = j + 1;
+                    },
+                    Cell::Symbol(_) => symbols.push(index),
+                    Cell::Empty => {},
+                }
+                if !matches!(cell, Cell::Digit(_)) {
+                    numbers.extend(current.take());
+                }
+            }
+            numbers.extend(current.take());
+        }
+
+        return Schematic { grid, numbers, symbols };
+    }
+
+    fn cells(&self, span: Span) -> impl Iterator<Item = GridIndex> + '_ {
+        return (span.start..span.end).map(move |j| self.grid.at(span.row, j));
+    }
+
+    fn touching(&self) -> Touching {
+        let mut by_number = vec![];
+        let mut by_symbol: BTreeMap<GridIndex, Vec<usize>> = self.symbols.iter().map(|symbol| (*symbol, vec![])).collect();
+
+        for (number, span) in self.numbers.iter().enumerate() {
+            let mut symbols = self.cells(*span)
+                .flat_map(|index| index.neighbours())
+                .filter(|index| matches!(self.grid.get(*index), Some(Cell::Symbol(_))))
+                .collect::<Vec<GridIndex>>();
+            symbols.sort();
+            symbols.dedup();
+
+            for symbol in symbols.iter() {
+                by_symbol.get_mut(symbol).unwrap().push(number);
+            }
+            by_number.push(symbols);
+        }
+
+        return Touching { by_number, by_symbol };
+    }
+
+    fn symbol(&self, index: GridIndex) -> char {
+        match self.grid.get(index) {
+            Some(Cell::Symbol(c)) => return *c,
+            cell => panic!("no symbol at {}: {:?}", index, cell),
+        }
+    }
+}
+
+fn solve_part1(content: String) -> u64 {
+    let schematic = Schematic::parse(&content);
+    let touching = schematic.touching();
+
+    return schematic.numbers
+        .iter()
+        .enumerate()
+        .filter(|(number, _)| !touching.symbols(*number).is_empty())
+        .map(|(_, span)| span.value)
+        .sum();
+}
+
+fn solve_part2(content: String) -> u64 {
+    let schematic = Schematic::parse(&content);
+    let touching = schematic.touching();
+
+    return schematic.symbols
+        .iter()
+        .filter(|symbol| schematic.symbol(**symbol) == '*')
+        .map(|symbol| touching.numbers(*symbol))
+        .filter(|numbers| numbers.len() == 2)
+        .map(|numbers| numbers.iter().map(|number| schematic.numbers[*number].value).product::<u64>())
+        .sum();
+}
+
+fn print_touching(content: &str) {
+    let schematic = Schematic::parse(content);
+    let touching = schematic.touching();
+
+    for (number, span) in schematic.numbers.iter().enumerate() {
+        let symbols = touching.symbols(number)
+            .iter()
+            .map(|symbol| format!("{} at {}", schematic.symbol(*symbol), symbol))
+            .collect::<Vec<String>>();
+        println!("{} at row {}, columns {}..{}: {}", span.value, span.row, span.start, span.end, if symbols.is_empty() { "-".to_string() } else { symbols.join(", ") });
+    }
+}
+
+fn main() {
+    let args = Cli::parse();
+    println!("input: {:?}", args.path);
+
+    let content = std::fs::read_to_string(&args.path).expect("could not read file");
+
+    if args.touching {
+        print_touching(&content);
+    }
+
+    println!("part1: {}", solve_part1(content.clone()));
+    println!("part2: {}", solve_part2(content.clone()));
+}
+
+#[cfg(test)]
+mod schematic_tests {
+    use crate::*;
+
+    const EXAMPLE: &str = "467..114..\n...*......\n..35..633.\n......#...\n617*......\n.....+.58.\n..592.....\n......755.\n...$.*....\n.664.598..";
+
+    #[test]
+    fn parses_number_spans() {
+        let schematic = Schematic::parse(EXAMPLE);
+
+        assert_eq!(schematic.numbers.len(), 10);
+        assert_eq!(schematic.numbers[0], Span { value: 467, row: 0, start: 0, end: 3 });
+        assert_eq!(schematic.numbers[3], Span { value: 633, row: 2, start: 6, end: 9 });
+        assert_eq!(schematic.symbols.len(), 6);
+    }
+
+    #[test]
+    fn neighbours_stay_inside_the_grid() {
+        let grid: Grid<Cell> = Grid::new(3, 4);
+
+        assert_eq!(grid.at(0, 0).neighbours().count(), 3);
+        assert_eq!(grid.at(1, 3).neighbours().count(), 5);
+        assert_eq!(grid.at(1, 1).neighbours().count(), 8);
+    }
+
+    #[test]
+    fn maps_numbers_to_symbols() {
+        let schematic = Schematic::parse(EXAMPLE);
+        let touching = schematic.touching();
+
+        // 114 and 58 touch nothing
+        assert!(touching.symbols(1).is_empty());
+        assert_eq!(touching.symbols(0), &[schematic.grid.at(1, 3)]);
+        assert_eq!(touching.numbers(schematic.grid.at(1, 3)), &[0, 2]);
+        assert_eq!(touching.numbers(schematic.grid.at(0, 0)), &[] as &[usize]);
+    }
+
+    #[test]
+    fn solves_example() {
+        assert_eq!(solve_part1(EXAMPLE.to_string()), 4361);
+        assert_eq!(solve_part2(EXAMPLE.to_string()), 467835);
+    }
+
+    #[test]
+    #[should_panic(expected = "number at line 2, column 2 does not fit in 64 bits")]
+    fn parse_rejects_numbers_that_overflow() {
+        Schematic::parse("..\n.18446744073709551616*");
+    }
+}
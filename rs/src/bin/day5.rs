@@ -0,0 +1,376 @@
+use clap::Parser;
+use std::ops::Range;
+
+#[derive(Parser)]
+struct Cli {
+    path: std::path::PathBuf,
+
+    /// List the seed ranges whose locations fall in START..END
+    #[arg(long, value_parser = parse_range)]
+    reverse: Option<Range<u64>>,
+
+    /// Print the value of these seeds at every stage
+    #[arg(long, value_delimiter = ',')]
+    explain: Vec<u64>,
+}
+
+fn parse_range(text: &str) -> Result<Range<u64>, String> {
+    let (start, end) = text.split_once("..").ok_or(format!("expected START..END, got {:?}", text))?;
+    let start = start.trim().parse::<u64>().map_err(|error| format!("invalid start {:?}: {}", start, error))?;
+    let end = end.trim().parse::<u64>().map_err(|error| format!("invalid end {:?}: {}", end, error))?;
+    if start >= end {
+        return Err(format!("empty range {}..{}", start, end));
+    }
+    return Ok(start..end);
+}
+
+// values in start..end are moved by offset
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Piece {
+    start: u64,
+    end: u64,
+    offset: i64,
+}
+
+fn shift(value: u64, offset: i64) -> u64 {
+    return value.checked_add_signed(offset).expect("mapped value out of range");
+}
+
+// sort ranges and join the ones that overlap or touch
+fn merge_ranges(mut ranges: Vec<Range<u64>>) -> Vec<Range<u64>> {
+    ranges.retain(|range| !range.is_empty());
+    ranges.sort_by_key(|range| range.start);
+
+    let mut merged: Vec<Range<u64>> = vec![];
+    for range in ranges {
+        match merged.last_mut() {
+            Some(last) if range.start <= last.end => last.end = last.end.max(range.end),
+            _ => merged.push(range),
+        }
+    }
+    return merged;
+}
+
+fn intersect_ranges(first: &[Range<u64>], second: &[Range<u64>]) -> Vec<Range<u64>> {
+    let mut result = vec![];
+    for a in first {
+        for b in second {
+            result.push(a.start.max(b.start)..a.end.min(b.end));
+        }
+    }
+    return merge_ranges(result);
+}
+
+// piecewise-linear map on u64, every value outside the pieces maps to itself
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+struct IntervalMap {
+    // sorted and disjoint, never with a zero offset
+    pieces: Vec<Piece>,
+}
+
+impl IntervalMap {
+    fn identity() -> Self {
+        return IntervalMap { pieces: vec![] };
+    }
+
+    fn new(mut pieces: Vec<Piece>) -> Self {
+        pieces.retain(|piece| piece.start < piece.end);
+        pieces.sort_by_key(|piece| piece.start);
+        if let Some(pair) = pieces.windows(2).find(|pair| pair[0].end > pair[1].start) {
+            panic!("overlapping ranges {}..{} and {}..{}", pair[0].start, pair[0].end, pair[1].start, pair[1].end);
+        }
+
+        // drop identity pieces and join neighbours that move by the same amount
+        let mut joined: Vec<Piece> = vec![];
+        for piece in pieces.into_iter().filter(|piece| piece.offset != 0) {
+            match joined.last_mut() {
+                Some(last) if last.end == piece.start && last.offset == piece.offset => last.end = piece.end,
+                _ => joined.push(piece),
+            }
+        }
+        return IntervalMap { pieces: joined };
+    }
+
+    // parse "destination source length" lines
+    fn parse(lines: &[&str]) -> Self {
+        let pieces = lines.iter().map(|line| {
+            let numbers = line.split_whitespace().map(|s| s.parse::<u64>().expect("invalid number")).collect::<Vec<u64>>();
+            if numbers.len() != 3 {
+                panic!("expected destination, source and length in {:?}", line);
+            }
+            let (destination, source, length) = (numbers[0], numbers[1], numbers[2]);
+            if source.checked_add(length).is_none() || destination.checked_add(length).is_none() {
+                panic!("range does not fit in 64 bits in {:?}", line);
+            }
+            let offset = i64::try_from(destination as i128 - source as i128).unwrap_or_else(|_| panic!("offset does not fit in 64 bits in {:?}", line));
+            return Piece { start: source, end: source + length, offset };
+        }).collect();
+        return IntervalMap::new(pieces);
+    }
+
+    // the pieces plus the identity gaps between them, covering all of u64
+    fn segments(&self) -> Vec<Piece> {
+        let mut segments = vec![];
+        let mut cursor = 0;
+        for piece in self.pieces.iter() {
+            if cursor < piece.start {
+                segments.push(Piece { start: cursor, end: piece.start, offset: 0 });
+            }
+            segments.push(*piece);
+            cursor = piece.end;
+        }
+        if cursor < u64::MAX {
+            segments.push(Piece { start: cursor, end: u64::MAX, offset: 0 });
+        }
+        return segments;
+    }
+
+    fn get(&self, value: u64) -> u64 {
+        let index = self.pieces.partition_point(|piece| piece.end <= value);
+        match self.pieces.get(index) {
+            Some(piece) if piece.start <= value => return shift(value, piece.offset),
+            _ => return value,
+        }
+    }
+
+    // map every value in the ranges, splitting them where the offset changes
+    fn apply(&self, ranges: &[Range<u64>]) -> Vec<Range<u64>> {
+        let segments = self.segments();
+        let mut result = vec![];
+        for range in ranges {
+            for segment in segments.iter().filter(|segment| segment.start < range.end && range.start < segment.end) {
+                let start = segment.start.max(range.start);
+                let end = segment.end.min(range.end);
+                result.push(shift(start, segment.offset)..shift(end, segment.offset));
+            }
+        }
+        return merge_ranges(result);
+    }
+
+    // every value that maps into the ranges; several pieces can land on the same values
+    fn preimage(&self, ranges: &[Range<u64>]) -> Vec<Range<u64>> {
+        let mut result = vec![];
+        for segment in self.segments() {
+            let image = shift(segment.start, segment.offset)..shift(segment.end, segment.offset);
+            for range in ranges.iter().filter(|range| range.start < image.end && image.start < range.end) {
+                let start = range.start.max(image.start);
+                let end = range.end.min(image.end);
+                result.push(shift(start, -segment.offset)..shift(end, -segment.offset));
+            }
+        }
+        return merge_ranges(result);
+    }
+
+    // the map that applies self first and then next
+    fn then(&self, next: &IntervalMap) -> IntervalMap {
+        let next_segments = next.segments();
+        let mut pieces = vec![];
+        for segment in self.segments() {
+            let image = shift(segment.start, segment.offset)..shift(segment.end, segment.offset);
+            for other in next_segments.iter().filter(|other| other.start < image.end && image.start < other.end) {
+                let start = other.start.max(image.start);
+                let end = other.end.min(image.end);
+                pieces.push(Piece {
+                    start: shift(start, -segment.offset),
+                    end: shift(end, -segment.offset),
+                    offset: segment.offset + other.offset,
+                });
+            }
+        }
+        return IntervalMap::new(pieces);
+    }
+}
+
+#[derive(Debug)]
+struct Stage {
+    from: String,
+    to: String,
+    map: IntervalMap,
+}
+
+#[derive(Debug)]
+struct Almanac {
+    seeds: Vec<u64>,
+    stages: Vec<Stage>,
+}
+
+impl Almanac {
+    fn parse(content: &str) -> Self {
+        let mut sections = content.trim().split("\n\n");
+
+        let seeds = sections.next()
+            .and_then(|line| line.strip_prefix("seeds:"))
+            .expect("almanac should start with seeds")
+            .split_whitespace()
+            .map(|s| s.parse::<u64>().expect("invalid seed"))
+            .collect::<Vec<u64>>();
+
+        // part two reads the seeds as pairs of start and length
+        if seeds.len() % 2 != 0 {
+            panic!("expected pairs of seed starts and lengths, got an odd number ({})", seeds.len());
+        }
+        if let Some(pair) = seeds.chunks(2).find(|pair| pair[0].checked_add(pair[1]).is_none()) {
+            panic!("seed range {} {} does not fit in 64 bits", pair[0], pair[1]);
+        }
+
+        let stages = sections.map(|section| {
+            let lines = section.lines().collect::<Vec<&str>>();
+            let (from, to) = lines[0]
+                .strip_suffix(" map:")
+                .and_then(|name| name.split_once("-to-"))
+                .unwrap_or_else(|| panic!("invalid map header {:?}", lines[0]));
+            return Stage { from: from.to_string(), to: to.to_string(), map: IntervalMap::parse(&lines[1..]) };
+        }).collect::<Vec<Stage>>();
+
+        if let Some(pair) = stages.windows(2).find(|pair| pair[0].to != pair[1].from) {
+            panic!("{}-to-{} map is followed by {}-to-{}", pair[0].from, pair[0].to, pair[1].from, pair[1].to);
+        }
+
+        return Almanac { seeds, stages };
+    }
+
+    // seed to location in one map
+    fn composed(&self) -> IntervalMap {
+        return self.stages.iter().fold(IntervalMap::identity(), |composed, stage| composed.then(&stage.map));
+    }
+
+    fn seed_ranges(&self) -> Vec<Range<u64>> {
+        return self.seeds.chunks(2).map(|pair| pair[0]..pair[0] + pair[1]).collect();
+    }
+
+    // the parts of the seed ranges that end up in the locations
+    fn seeds_reaching(&self, locations: Range<u64>) -> Vec<Range<u64>> {
+        let seeds = self.composed().preimage(&[locations]);
+        return intersect_ranges(&seeds, &self.seed_ranges());
+    }
+
+    // the value of a seed at every stage, starting with the seed itself
+    fn trace(&self, seed: u64) -> Vec<(&str, u64)> {
+        let first = self.stages.first().map(|stage| stage.from.as_str()).unwrap_or("seed");
+        let mut values = vec![(first, seed)];
+        let mut value = seed;
+        for stage in self.stages.iter() {
+            value = stage.map.get(value);
+            values.push((stage.to.as_str(), value));
+        }
+        return values;
+    }
+}
+
+fn solve_part1(content: String) -> u64 {
+    let almanac = Almanac::parse(&content);
+    let map = almanac.composed();
+    return almanac.seeds.iter().map(|seed| map.get(*seed)).min().unwrap();
+}
+
+fn solve_part2(content: String) -> u64 {
+    let almanac = Almanac::parse(&content);
+    let locations = almanac.composed().apply(&almanac.seed_ranges());
+    return locations.first().expect("no seed ranges").start;
+}
+
+fn main() {
+    let args = Cli::parse();
+    println!("input: {:?}", args.path);
+
+    let content = std::fs::read_to_string(&args.path).expect("could not read file");
+
+    if !args.explain.is_empty() || args.reverse.is_some() {
+        let almanac = Almanac::parse(&content);
+        for seed in args.explain.iter() {
+            let trace = almanac.trace(*seed).iter().map(|(name, value)| format!("{} {}", name, value)).collect::<Vec<String>>();
+            println!("{}", trace.join(", "));
+        }
+        if let Some(locations) = args.reverse {
+            let map = almanac.composed();
+            for seeds in almanac.seeds_reaching(locations.clone()) {
+                let reached = map.apply(std::slice::from_ref(&seeds)).iter().map(|range| format!("{}..{}", range.start, range.end)).collect::<Vec<String>>();
+                println!("seeds {}..{} -> locations {}", seeds.start, seeds.end, reached.join(", "));
+            }
+        }
+    }
+
+    println!("part1: {}", solve_part1(content.clone()));
+    println!("part2: {}", solve_part2(content.clone()));
+}
+
+#[cfg(test)]
+mod interval_tests {
+    use crate::*;
+
+    const EXAMPLE: &str = "seeds: 79 14 55 13\n\nseed-to-soil map:\n50 98 2\n52 50 48\n\nsoil-to-fertilizer map:\n0 15 37\n37 52 2\n39 0 15\n\nfertilizer-to-water map:\n49 53 8\n0 11 42\n42 0 7\n57 7 4\n\nwater-to-light map:\n88 18 7\n18 25 70\n\nlight-to-temperature map:\n45 77 23\n81 45 19\n68 64 13\n\ntemperature-to-humidity map:\n0 69 1\n1 0 69\n\nhumidity-to-location map:\n60 56 37\n56 93 4";
+
+    #[test]
+    fn apply_splits_ranges_at_boundaries() {
+        let map = IntervalMap::parse(&["50 98 2", "52 50 48"]);
+
+        assert_eq!(map.get(98), 50);
+        assert_eq!(map.get(10), 10);
+        assert_eq!(map.apply(&[45..55, 10..20]), vec![10..20, 45..50, 52..57]);
+        // 95..98 moves up by two and meets 100..102, 98..100 moves down to 50..52
+        assert_eq!(map.apply(&[95..102, 0..1]), vec![0..1, 50..52, 97..102]);
+    }
+
+    #[test]
+    #[should_panic(expected = "expected pairs of seed starts and lengths, got an odd number (1)")]
+    fn parse_rejects_odd_seed_counts() {
+        Almanac::parse("seeds: 1\n\nseed-to-soil map:\n50 98 2");
+    }
+
+    #[test]
+    #[should_panic(expected = "range does not fit in 64 bits in \"0 18446744073709551615 2\"")]
+    fn parse_rejects_ranges_past_the_end() {
+        IntervalMap::parse(&["0 18446744073709551615 2"]);
+    }
+
+    #[test]
+    #[should_panic(expected = "offset does not fit in 64 bits in \"18446744073709551614 0 1\"")]
+    fn parse_rejects_offsets_that_do_not_fit() {
+        IntervalMap::parse(&["18446744073709551614 0 1"]);
+    }
+
+    #[test]
+    fn composition_matches_stage_by_stage_lookup() {
+        let almanac = Almanac::parse(EXAMPLE);
+        let map = almanac.composed();
+
+        for seed in 0..120 {
+            let expected = almanac.stages.iter().fold(seed, |value, stage| stage.map.get(value));
+            assert_eq!(map.get(seed), expected, "seed {}", seed);
+        }
+    }
+
+    #[test]
+    fn solves_example() {
+        assert_eq!(solve_part1(EXAMPLE.to_string()), 35);
+        assert_eq!(solve_part2(EXAMPLE.to_string()), 46);
+    }
+
+    #[test]
+    fn traces_every_stage() {
+        let almanac = Almanac::parse(EXAMPLE);
+
+        assert_eq!(almanac.trace(79), vec![
+            ("seed", 79), ("soil", 81), ("fertilizer", 81), ("water", 81),
+            ("light", 74), ("temperature", 78), ("humidity", 78), ("location", 82),
+        ]);
+        assert_eq!(almanac.trace(82).last(), Some(&("location", 46)));
+    }
+
+    #[test]
+    fn reverse_finds_seeds_for_locations() {
+        let almanac = Almanac::parse(EXAMPLE);
+        let map = almanac.composed();
+
+        assert_eq!(almanac.seeds_reaching(46..47), vec![82..83]);
+        assert!(almanac.seeds_reaching(0..46).is_empty());
+
+        // every seed in the preimage maps into the range and no other seed does
+        let locations = 50..60;
+        let seeds = map.preimage(std::slice::from_ref(&locations));
+        for seed in 0..200 {
+            let inside = seeds.iter().any(|range| range.contains(&seed));
+            assert_eq!(inside, locations.contains(&map.get(seed)), "seed {}", seed);
+        }
+    }
+}
@@ -0,0 +1,170 @@
+use clap::Parser;
+
+#[derive(Parser)]
+struct Cli {
+    path: std::path::PathBuf,
+
+    /// Compare the closed form against brute force on small races
+    #[arg(long)]
+    check: bool,
+}
+
+// races up to this time are small enough to try every hold time
+const BRUTE_FORCE_LIMIT: u128 = 1_000_000;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Race {
+    time: u128,
+    record: u128,
+}
+
+impl Race {
+    // holding for `hold` ms leaves time - hold ms to travel at hold mm/ms
+    fn wins(&self, hold: u128) -> bool {
+        match hold.checked_mul(self.time - hold) {
+            Some(distance) => return distance > self.record,
+            // more than any u128 record
+            None => return true,
+        }
+    }
+
+    // the shortest winning hold time, the longest one is time - first_win by symmetry
+    fn first_win(&self) -> Option<u128> {
+        // the distance peaks at half the time
+        let middle = self.time / 2;
+        if !self.wins(middle) {
+            return None;
+        }
+
+        // hold^2 - time * hold + record < 0 between the roots (time -+ sqrt(time^2 - 4 record)) / 2,
+        // times past 2^64 don't square in u128 so those bisect instead
+        let discriminant = self.time.checked_mul(self.time).zip(self.record.checked_mul(4)).map(|(square, record)| square - record);
+        let mut hold = match discriminant {
+            Some(discriminant) => (self.time - discriminant.isqrt()) / 2,
+            None => {
+                let (mut low, mut high) = (0, middle);
+                while low < high {
+                    let hold = low + (high - low) / 2;
+                    if self.wins(hold) { high = hold } else { low = hold + 1 }
+                }
+                low
+            },
+        };
+
+        // the rounded root can be one off in either direction
+        while !self.wins(hold) {
+            hold += 1;
+        }
+        while hold > 0 && self.wins(hold - 1) {
+            hold -= 1;
+        }
+        return Some(hold);
+    }
+
+    fn ways_to_win(&self) -> u128 {
+        return self.first_win().map(|hold| self.time - 2 * hold + 1).unwrap_or(0);
+    }
+
+    fn ways_to_win_brute_force(&self) -> u128 {
+        return (0..=self.time).filter(|hold| self.wins(*hold)).count() as u128;
+    }
+}
+
+fn parse_numbers(line: &str) -> Vec<u128> {
+    let (_, numbers) = line.split_once(':').expect("expected a label before the numbers");
+    return numbers.split_whitespace().map(|s| s.parse::<u128>().expect("invalid number")).collect();
+}
+
+fn parse_races(content: &str) -> Vec<Race> {
+    let lines = content.lines().collect::<Vec<&str>>();
+    let times = parse_numbers(lines[0]);
+    let records = parse_numbers(lines[1]);
+    return times.into_iter().zip(records).map(|(time, record)| Race { time, record }).collect();
+}
+
+fn solve_part1(content: String) -> u128 {
+    return parse_races(&content).iter().map(|race| race.ways_to_win()).product();
+}
+
+fn solve_part2(content: String) -> u128 {
+    // the spaces are bad kerning, every line is a single number
+    let content = content.lines().map(|line| line.replace(' ', "").replace(':', ": ")).collect::<Vec<String>>().join("\n");
+    return solve_part1(content);
+}
+
+// every race in the input plus all small times and interesting records,
+// returns the races where both methods disagree
+fn cross_check(content: &str) -> (usize, Vec<(Race, u128, u128)>) {
+    let mut races = parse_races(content).into_iter().filter(|race| race.time <= BRUTE_FORCE_LIMIT).collect::<Vec<Race>>();
+    for time in 0..=60 {
+        for record in 0..=time * time / 4 + 1 {
+            races.push(Race { time, record });
+        }
+    }
+
+    let mismatches = races.iter()
+        .map(|race| (*race, race.ways_to_win(), race.ways_to_win_brute_force()))
+        .filter(|(_, closed, brute)| closed != brute)
+        .collect();
+    return (races.len(), mismatches);
+}
+
+fn main() {
+    let args = Cli::parse();
+    println!("input: {:?}", args.path);
+
+    let content = std::fs::read_to_string(&args.path).expect("could not read file");
+
+    if args.check {
+        let (checked, mismatches) = cross_check(&content);
+        for (race, closed, brute) in mismatches.iter() {
+            println!("time {} record {}: closed form {}, brute force {}", race.time, race.record, closed, brute);
+        }
+        println!("checked {} races, {} mismatches", checked, mismatches.len());
+        if !mismatches.is_empty() {
+            std::process::exit(1);
+        }
+    }
+
+    println!("part1: {}", solve_part1(content.clone()));
+    println!("part2: {}", solve_part2(content.clone()));
+}
+
+#[cfg(test)]
+mod race_tests {
+    use crate::*;
+
+    const EXAMPLE: &str = "Time:      7  15   30\nDistance:  9  40  200";
+
+    #[test]
+    fn solves_example() {
+        assert_eq!(solve_part1(EXAMPLE.to_string()), 288);
+        assert_eq!(solve_part2(EXAMPLE.to_string()), 71503);
+    }
+
+    #[test]
+    fn ties_with_the_record_do_not_win() {
+        // 10 * 20 == 200 exactly
+        assert_eq!(Race { time: 30, record: 200 }.first_win(), Some(11));
+        // the best possible distance only ties
+        assert_eq!(Race { time: 10, record: 25 }.ways_to_win(), 0);
+        assert_eq!(Race { time: 10, record: 24 }.ways_to_win(), 1);
+    }
+
+    #[test]
+    fn closed_form_matches_brute_force() {
+        let (checked, mismatches) = cross_check(EXAMPLE);
+        assert!(checked > 1000);
+        assert!(mismatches.is_empty(), "{:?}", mismatches);
+    }
+
+    #[test]
+    fn handles_times_that_do_not_square() {
+        let time = u128::MAX - 1;
+        let race = Race { time, record: 1 << 100 };
+        let hold = race.first_win().unwrap();
+
+        assert!(race.wins(hold) && !race.wins(hold - 1));
+        assert_eq!(race.ways_to_win(), time - 2 * hold + 1);
+    }
+}
@@ -0,0 +1,238 @@
+use clap::Parser;
+use std::collections::HashMap;
+use std::fmt;
+
+#[derive(Parser)]
+struct Cli {
+    path: std::path::PathBuf,
+
+    /// Explain every hand's category under these rules
+    #[arg(long)]
+    explain: Option<Preset>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, clap::ValueEnum)]
+enum Preset {
+    Standard,
+    Jokers,
+}
+
+// a hand is in this category when its sorted card counts start with at least `counts`
+#[derive(Debug, Clone, PartialEq)]
+struct Category {
+    name: String,
+    counts: Vec<usize>,
+}
+
+#[derive(Debug, Clone)]
+struct Rules {
+    // weakest card first
+    order: Vec<char>,
+    // cards that stand in for whichever card makes the best hand, but rank by `order` on ties
+    wildcards: Vec<char>,
+    // weakest category first
+    categories: Vec<Category>,
+}
+
+fn category(name: &str, counts: &[usize]) -> Category {
+    return Category { name: name.to_string(), counts: counts.to_vec() };
+}
+
+fn standard_categories() -> Vec<Category> {
+    return vec![
+        category("high card", &[]),
+        category("one pair", &[2]),
+        category("two pair", &[2, 2]),
+        category("three of a kind", &[3]),
+        category("full house", &[3, 2]),
+        category("four of a kind", &[4]),
+        category("five of a kind", &[5]),
+    ];
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Explanation {
+    hand: String,
+    category: String,
+    // (wildcards in the hand, card they all stand for, resulting hand)
+    substitution: Option<(Vec<char>, char, String)>,
+}
+
+impl fmt::Display for Explanation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.substitution {
+            Some((wildcards, card, hand)) => {
+                let wildcards = wildcards.iter().map(|wildcard| wildcard.to_string()).collect::<Vec<String>>();
+                write!(f, "{}: {} ({} as {}: {})", self.hand, self.category, wildcards.join(", "), card, hand)
+            },
+            None => write!(f, "{}: {}", self.hand, self.category),
+        }
+    }
+}
+
+impl Rules {
+    fn preset(preset: Preset) -> Self {
+        match preset {
+            Preset::Standard => return Rules {
+                order: "23456789TJQKA".chars().collect(),
+                wildcards: vec![],
+                categories: standard_categories(),
+            },
+            Preset::Jokers => return Rules {
+                order: "J23456789TQKA".chars().collect(),
+                wildcards: vec!['J'],
+                categories: standard_categories(),
+            },
+        }
+    }
+
+    fn rank(&self, card: char) -> usize {
+        return self.order.iter().position(|c| *c == card).unwrap_or_else(|| panic!("unknown card {:?}", card));
+    }
+
+    // index into `categories` without any wildcards
+    fn category_of(&self, hand: &str) -> usize {
+        let mut counts: HashMap<char, usize> = HashMap::new();
+        for card in hand.chars() {
+            *counts.entry(card).or_default() += 1;
+        }
+        let mut counts = counts.into_values().collect::<Vec<usize>>();
+        counts.sort_by(|a, b| b.cmp(a));
+
+        return self.categories
+            .iter()
+            .rposition(|category| category.counts.iter().enumerate().all(|(i, count)| counts.get(i).is_some_and(|c| c >= count)))
+            .unwrap_or_else(|| panic!("{} fits no category", hand));
+    }
+
+    // the best category over all ways to replace every wildcard in the hand, whatever its kind,
+    // by the same card
+    fn best_category(&self, hand: &str) -> (usize, Option<(Vec<char>, char, String)>) {
+        let mut best = (self.category_of(hand), None);
+        let wildcards = self.wildcards.iter().filter(|wildcard| hand.contains(**wildcard)).cloned().collect::<Vec<char>>();
+        if wildcards.is_empty() {
+            return best;
+        }
+
+        for card in self.order.iter().filter(|card| !self.wildcards.contains(card)) {
+            let substituted = hand.chars().map(|c| if wildcards.contains(&c) { *card } else { c }).collect::<String>();
+            let category = self.category_of(&substituted);
+            if category > best.0 {
+                best = (category, Some((wildcards.clone(), *card, substituted)));
+            }
+        }
+        return best;
+    }
+
+    // category first, then the cards in order, each as a digit in base `order.len()`
+    fn key(&self, hand: &str) -> u128 {
+        let base = self.order.len() as u128;
+        let (category, _) = self.best_category(hand);
+        return hand.chars().fold(category as u128, |key, card| key * base + self.rank(card) as u128);
+    }
+
+    fn explain(&self, hand: &str) -> Explanation {
+        let (category, substitution) = self.best_category(hand);
+        return Explanation { hand: hand.to_string(), category: self.categories[category].name.clone(), substitution };
+    }
+}
+
+fn parse_hands(content: &str) -> Vec<(String, u64)> {
+    return content
+        .lines()
+        .map(|line| {
+            let (hand, bid) = line.split_once(' ').expect("expected a hand and a bid");
+            return (hand.to_string(), bid.trim().parse::<u64>().expect("invalid bid"));
+        })
+        .collect();
+}
+
+fn total_winnings(content: &str, rules: &Rules) -> u64 {
+    let mut hands = parse_hands(content).into_iter().map(|(hand, bid)| (rules.key(&hand), bid)).collect::<Vec<(u128, u64)>>();
+    hands.sort();
+    return hands.iter().enumerate().map(|(i, (_, bid))| (i as u64 + 1) * bid).sum();
+}
+
+fn solve_part1(content: String) -> u64 {
+    return total_winnings(&content, &Rules::preset(Preset::Standard));
+}
+
+fn solve_part2(content: String) -> u64 {
+    return total_winnings(&content, &Rules::preset(Preset::Jokers));
+}
+
+fn main() {
+    let args = Cli::parse();
+    println!("input: {:?}", args.path);
+
+    let content = std::fs::read_to_string(&args.path).expect("could not read file");
+
+    if let Some(preset) = args.explain {
+        let rules = Rules::preset(preset);
+        for (hand, _) in parse_hands(&content) {
+            println!("{}", rules.explain(&hand));
+        }
+    }
+
+    println!("part1: {}", solve_part1(content.clone()));
+    println!("part2: {}", solve_part2(content.clone()));
+}
+
+#[cfg(test)]
+mod ranking_tests {
+    use crate::*;
+
+    const EXAMPLE: &str = "32T3K 765\nT55J5 684\nKK677 28\nKTJJT 220\nQQQJA 483";
+
+    #[test]
+    fn solves_example() {
+        assert_eq!(solve_part1(EXAMPLE.to_string()), 6440);
+        assert_eq!(solve_part2(EXAMPLE.to_string()), 5905);
+    }
+
+    #[test]
+    fn explains_joker_substitutions() {
+        let rules = Rules::preset(Preset::Jokers);
+
+        assert_eq!(rules.explain("KTJJT").to_string(), "KTJJT: four of a kind (J as T: KTTTT)");
+        assert_eq!(rules.explain("JJJJJ").to_string(), "JJJJJ: five of a kind");
+        assert_eq!(rules.explain("32T3K").to_string(), "32T3K: one pair");
+        assert_eq!(Rules::preset(Preset::Standard).explain("KTJJT").to_string(), "KTJJT: two pair");
+    }
+
+    #[test]
+    fn jokers_rank_lowest_on_ties() {
+        let rules = Rules::preset(Preset::Jokers);
+
+        // both are four of a kind, but J is weaker than Q
+        assert!(rules.key("JKKK2") < rules.key("QQQQ2"));
+        assert!(rules.key("JJJJJ") < rules.key("22222"));
+    }
+
+    #[test]
+    fn categories_are_configurable() {
+        // a deck without pairs beyond three of a kind, and 1 as a wildcard
+        let rules = Rules {
+            order: "1234".chars().collect(),
+            wildcards: vec!['1'],
+            categories: vec![category("nothing", &[]), category("pair", &[2]), category("triple", &[3])],
+        };
+
+        assert_eq!(rules.explain("2341").to_string(), "2341: pair (1 as 2: 2342)");
+        assert_eq!(rules.explain("2211").category, "triple");
+    }
+
+    #[test]
+    fn different_wildcards_stand_for_the_same_card() {
+        let rules = Rules {
+            order: "W23456789TJQKA".chars().collect(),
+            wildcards: vec!['J', 'W'],
+            categories: standard_categories(),
+        };
+
+        // one of each wildcard joins the pair of kings
+        assert_eq!(rules.explain("KJ2WK").to_string(), "KJ2WK: four of a kind (J, W as K: KK2KK)");
+        assert_eq!(rules.explain("JWJW3").to_string(), "JWJW3: five of a kind (J, W as 3: 33333)");
+        assert_eq!(rules.explain("J2W34").category, "three of a kind");
+    }
+}
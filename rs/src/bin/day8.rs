@@ -0,0 +1,256 @@
+#[path = "../numbers.rs"]
+mod numbers;
+
+use clap::Parser;
+use numbers::{combine_congruences, lcm};
+use std::collections::HashMap;
+use std::fmt;
+
+#[derive(Parser)]
+struct Cli {
+    path: std::path::PathBuf,
+
+    /// Print every ghost's cycle and how they were combined
+    #[arg(long)]
+    explain: bool,
+}
+
+#[derive(Debug)]
+struct Network {
+    // 0 for L, 1 for R
+    instructions: Vec<usize>,
+    names: Vec<String>,
+    ids: HashMap<String, usize>,
+    nodes: Vec<[usize; 2]>,
+}
+
+impl Network {
+    fn parse(content: &str) -> Self {
+        let (instructions, nodes) = content.trim().split_once("\n\n").expect("expected instructions and nodes");
+        let instructions = instructions.trim().chars().map(|c| match c {
+            'L' => 0,
+            'R' => 1,
+            c => panic!("invalid instruction {:?}", c),
+        }).collect::<Vec<usize>>();
+
+        let lines = nodes.lines().map(|line| {
+            let (name, next) = line.split_once(" = ").unwrap_or_else(|| panic!("invalid node {:?}", line));
+            let (left, right) = next
+                .trim_matches(|c| c == '(' || c == ')')
+                .split_once(", ")
+                .unwrap_or_else(|| panic!("invalid node {:?}", line));
+            return (name, left, right);
+        }).collect::<Vec<(&str, &str, &str)>>();
+
+        let names = lines.iter().map(|(name, _, _)| name.to_string()).collect::<Vec<String>>();
+        let ids = names.iter().enumerate().map(|(id, name)| (name.clone(), id)).collect::<HashMap<String, usize>>();
+        let id = |name: &str| *ids.get(name).unwrap_or_else(|| panic!("undefined node {:?}", name));
+        let nodes = lines.iter().map(|(_, left, right)| [id(left), id(right)]).collect();
+
+        return Network { instructions, names, ids, nodes };
+    }
+
+    // follow the instructions from `start` until a (node, instruction index) pair repeats,
+    // noting every step that lands on an end node
+    fn walk(&self, start: usize, is_end: impl Fn(&str) -> bool) -> Ghost {
+        let mut seen: HashMap<(usize, usize), u64> = HashMap::new();
+        let mut hits = vec![];
+        let mut node = start;
+        let mut step: u64 = 0;
+
+        loop {
+            let instruction = step as usize % self.instructions.len();
+            if let Some(first) = seen.insert((node, instruction), step) {
+                let (transient, cycle_hits) = hits.iter().partition(|hit| **hit < first);
+                return Ghost { start: self.names[start].clone(), transient, cycle_start: first, period: step - first, cycle_hits };
+            }
+            if is_end(&self.names[node]) {
+                hits.push(step);
+            }
+            node = self.nodes[node][self.instructions[instruction]];
+            step += 1;
+        }
+    }
+}
+
+// the steps at which a walk is on an end node: each transient hit once, and every
+// cycle hit again after each period
+#[derive(Debug, Clone, PartialEq)]
+struct Ghost {
+    start: String,
+    transient: Vec<u64>,
+    cycle_start: u64,
+    period: u64,
+    cycle_hits: Vec<u64>,
+}
+
+impl Ghost {
+    fn hits_at(&self, step: u64) -> bool {
+        return self.transient.contains(&step)
+            || self.cycle_hits.iter().any(|hit| step >= *hit && (step - hit).is_multiple_of(self.period));
+    }
+
+    fn first_hit(&self) -> Option<u64> {
+        return self.transient.iter().chain(self.cycle_hits.iter()).min().copied();
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Method {
+    // some ghost was still on its way into its cycle
+    Transient,
+    // every ghost ends exactly once per cycle, at a multiple of its period
+    Lcm,
+    Crt,
+}
+
+impl fmt::Display for Method {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Method::Transient => write!(f, "before all cycles started"),
+            Method::Lcm => write!(f, "least common multiple of the periods"),
+            Method::Crt => write!(f, "chinese remainder theorem"),
+        }
+    }
+}
+
+// the first step at which every ghost is on an end node
+fn first_common_step(ghosts: &[Ghost]) -> Option<(u128, Method)> {
+    // a common step before some ghost's cycle starts is one of that ghost's transient hits
+    let mut transient = ghosts.iter().flat_map(|ghost| ghost.transient.iter().copied()).collect::<Vec<u64>>();
+    transient.sort();
+    if let Some(step) = transient.into_iter().find(|step| ghosts.iter().all(|ghost| ghost.hits_at(*step))) {
+        return Some((step as u128, Method::Transient));
+    }
+    if ghosts.iter().any(|ghost| ghost.cycle_hits.is_empty()) {
+        return None;
+    }
+
+    let aligned = ghosts.iter().all(|ghost| ghost.cycle_hits.len() == 1 && ghost.cycle_hits[0].is_multiple_of(ghost.period));
+    if aligned {
+        let period = ghosts.iter().fold(1, |period, ghost| lcm(period, ghost.period as u128));
+        let last = ghosts.iter().map(|ghost| ghost.cycle_hits[0] as u128).max().unwrap_or(0).max(1);
+        return Some((last.div_ceil(period) * period, Method::Lcm));
+    }
+
+    // try every combination of one cycle hit per ghost
+    let mut best: Option<u128> = None;
+    let mut choice = vec![0; ghosts.len()];
+    loop {
+        let mut combined = Some((0, 1));
+        for (ghost, i) in ghosts.iter().zip(choice.iter()) {
+            let hit = ghost.cycle_hits[*i] as i128;
+            combined = combined.and_then(|combined| combine_congruences(combined, (hit % ghost.period as i128, ghost.period as i128)));
+        }
+
+        if let Some((x, modulus)) = combined {
+            let (x, modulus) = (x as u128, modulus as u128);
+            let last = ghosts.iter().zip(choice.iter()).map(|(ghost, i)| ghost.cycle_hits[*i] as u128).max().unwrap_or(0);
+            let step = if x >= last { x } else { x + (last - x).div_ceil(modulus) * modulus };
+            best = Some(best.map_or(step, |best| best.min(step)));
+        }
+
+        // next combination, like counting with mixed radices
+        let Some(position) = (0..ghosts.len()).find(|g| choice[*g] + 1 < ghosts[*g].cycle_hits.len()) else { break };
+        choice[position] += 1;
+        for earlier in choice.iter_mut().take(position) {
+            *earlier = 0;
+        }
+    }
+    return best.map(|step| (step, Method::Crt));
+}
+
+fn ghosts(network: &Network) -> Vec<Ghost> {
+    return network.names
+        .iter()
+        .filter(|name| name.ends_with('A'))
+        .map(|name| network.walk(network.ids[name], |node| node.ends_with('Z')))
+        .collect();
+}
+
+fn solve_part1(content: String) -> u64 {
+    let network = Network::parse(&content);
+    let start = *network.ids.get("AAA").expect("no AAA node");
+    return network.walk(start, |node| node == "ZZZ").first_hit().expect("ZZZ is never reached from AAA");
+}
+
+fn solve_part2(content: String) -> u128 {
+    let network = Network::parse(&content);
+    let (step, _) = first_common_step(&ghosts(&network)).expect("the ghosts never all end at once");
+    return step;
+}
+
+fn explain(content: &str) {
+    let network = Network::parse(content);
+    let ghosts = ghosts(&network);
+    for ghost in ghosts.iter() {
+        println!("{}: ends at {:?} then {:?} every {} steps from step {}", ghost.start, ghost.transient, ghost.cycle_hits, ghost.period, ghost.cycle_start);
+    }
+    match first_common_step(&ghosts) {
+        Some((step, method)) => println!("all end at step {} ({})", step, method),
+        None => println!("the ghosts never all end at once"),
+    }
+}
+
+fn main() {
+    let args = Cli::parse();
+    println!("input: {:?}", args.path);
+
+    let content = std::fs::read_to_string(&args.path).expect("could not read file");
+
+    if args.explain {
+        explain(&content);
+    }
+
+    println!("part1: {}", solve_part1(content.clone()));
+    println!("part2: {}", solve_part2(content.clone()));
+}
+
+#[cfg(test)]
+mod ghost_tests {
+    use crate::*;
+
+    const GHOSTS: &str = "LR\n\n11A = (11B, XXX)\n11B = (XXX, 11Z)\n11Z = (11B, XXX)\n22A = (22B, XXX)\n22B = (22C, 22C)\n22C = (22Z, 22Z)\n22Z = (22B, 22B)\nXXX = (XXX, XXX)";
+
+    fn ghost(transient: &[u64], cycle_start: u64, period: u64, cycle_hits: &[u64]) -> Ghost {
+        return Ghost { start: "test".to_string(), transient: transient.to_vec(), cycle_start, period, cycle_hits: cycle_hits.to_vec() };
+    }
+
+    #[test]
+    fn solves_examples() {
+        assert_eq!(solve_part1("RL\n\nAAA = (BBB, CCC)\nBBB = (DDD, EEE)\nCCC = (ZZZ, GGG)\nDDD = (DDD, DDD)\nEEE = (EEE, EEE)\nGGG = (GGG, GGG)\nZZZ = (ZZZ, ZZZ)".to_string()), 2);
+        assert_eq!(solve_part1("LLR\n\nAAA = (BBB, BBB)\nBBB = (AAA, ZZZ)\nZZZ = (ZZZ, ZZZ)".to_string()), 6);
+        assert_eq!(solve_part2(GHOSTS.to_string()), 6);
+    }
+
+    #[test]
+    fn detects_cycles() {
+        let network = Network::parse(GHOSTS);
+        let ghosts = ghosts(&network);
+
+        assert_eq!(ghosts[0], Ghost { start: "11A".to_string(), transient: vec![], cycle_start: 1, period: 2, cycle_hits: vec![2] });
+        assert_eq!(ghosts[1], Ghost { start: "22A".to_string(), transient: vec![], cycle_start: 1, period: 6, cycle_hits: vec![3, 6] });
+        assert_eq!(first_common_step(&ghosts), Some((6, Method::Crt)));
+    }
+
+    #[test]
+    fn combines_aligned_cycles_with_lcm() {
+        let ghosts = [ghost(&[], 2, 4, &[4]), ghost(&[], 1, 6, &[6])];
+        assert_eq!(first_common_step(&ghosts), Some((12, Method::Lcm)));
+    }
+
+    #[test]
+    fn combines_offset_cycles_with_crt() {
+        assert_eq!(first_common_step(&[ghost(&[], 0, 4, &[1]), ghost(&[], 0, 6, &[3])]), Some((9, Method::Crt)));
+        // odd and even steps never meet
+        assert_eq!(first_common_step(&[ghost(&[], 0, 4, &[1]), ghost(&[], 0, 6, &[2])]), None);
+        // the cycle hit only counts from the step it first happens
+        assert_eq!(first_common_step(&[ghost(&[], 0, 4, &[1]), ghost(&[], 20, 5, &[21])]), Some((21, Method::Crt)));
+    }
+
+    #[test]
+    fn transient_hits_come_first() {
+        let ghosts = [ghost(&[3], 5, 4, &[6]), ghost(&[], 0, 3, &[0])];
+        assert_eq!(first_common_step(&ghosts), Some((3, Method::Transient)));
+    }
+}
@@ -0,0 +1,152 @@
+// Grids shared by the days that work on a map of cells, included with
+// `#[path = "../grid.rs"] mod grid;` so every day can add its own methods
+#![allow(dead_code)]
+
+use std::fmt;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Direction {
+    Up,
+    Down,
+    Left,
+    Right,
+}
+
+pub const DIRECTIONS: [Direction; 4] = [Direction::Up, Direction::Down, Direction::Left, Direction::Right];
+
+impl Direction {
+    pub fn turn_left(&self) -> Self {
+        match self {
+            Direction::Up => Direction::Left,
+            Direction::Down => Direction::Right,
+            Direction::Left => Direction::Down,
+            Direction::Right => Direction::Up,
+        }
+    }
+
+    pub fn turn_right(&self) -> Self {
+        match self {
+            Direction::Up => Direction::Right,
+            Direction::Down => Direction::Left,
+            Direction::Left => Direction::Up,
+            Direction::Right => Direction::Down,
+        }
+    }
+
+    pub fn opposite(&self) -> Self {
+        match self {
+            Direction::Up => Direction::Down,
+            Direction::Down => Direction::Up,
+            Direction::Left => Direction::Right,
+            Direction::Right => Direction::Left,
+        }
+    }
+
+    pub fn arrow(&self) -> char {
+        match self {
+            Direction::Up => '^',
+            Direction::Down => 'v',
+            Direction::Left => '<',
+            Direction::Right => '>',
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub struct GridIndex {
+    pub i: usize,
+    pub j: usize,
+
+    pub rows: usize,
+    pub cols: usize,
+}
+
+impl GridIndex {
+    pub fn step(self, direction: Direction) -> Option<Self> {
+        let mut new_index = self;
+        match direction {
+            Direction::Up => if self.i > 0 { new_index.i -= 1 } else { return None },
+            Direction::Down => if self.i < self.rows - 1 { new_index.i += 1 } else { return None },
+            Direction::Left => if self.j > 0 { new_index.j -= 1 } else { return None },
+            Direction::Right => if self.j < self.cols - 1 { new_index.j += 1 } else { return None },
+        }
+        return Some(new_index);
+    }
+
+    // all 8 surrounding cells that lie inside the grid
+    pub fn neighbours(self) -> impl Iterator<Item = GridIndex> {
+        return (-1isize..=1).flat_map(move |di| (-1isize..=1).map(move |dj| (di, dj)))
+            .filter(|&(di, dj)| di != 0 || dj != 0)
+            .filter_map(move |(di, dj)| {
+                let i = self.i.checked_add_signed(di).filter(|i| *i < self.rows)?;
+                let j = self.j.checked_add_signed(dj).filter(|j| *j < self.cols)?;
+                return Some(GridIndex { i, j, ..self });
+            });
+    }
+
+    pub fn manhattan_distance(&self, other: &GridIndex) -> usize {
+        return self.i.abs_diff(other.i) + self.j.abs_diff(other.j);
+    }
+}
+
+impl fmt::Display for GridIndex {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "({}, {})", self.i, self.j)
+    }
+}
+
+#[derive(Debug)]
+pub struct Grid<T> {
+    pub rows: usize,
+    pub cols: usize,
+    pub data: Vec<T>,
+}
+
+impl<T: Clone + Default> Grid<T> {
+    pub fn new(rows: usize, cols: usize) -> Self {
+        let data = vec![T::default(); rows * cols];
+        return Grid { rows, cols, data };
+    }
+
+    pub fn index(&self, index: GridIndex) -> usize {
+        return index.i * self.cols + index.j;
+    }
+
+    pub fn at(&self, i: usize, j: usize) -> GridIndex {
+        return GridIndex { i, j, rows: self.rows, cols: self.cols };
+    }
+
+    pub fn get(&self, index: GridIndex) -> Option<&T> {
+        if index.i >= self.rows || index.j >= self.cols {
+            return None;
+        }
+        return self.data.get(self.index(index));
+    }
+
+    pub fn get_mut(&mut self, index: GridIndex) -> Option<&mut T> {
+        if index.i >= self.rows || index.j >= self.cols {
+            return None;
+        }
+        let index1d = self.index(index);
+        return self.data.get_mut(index1d);
+    }
+
+    pub fn set(&mut self, index: GridIndex, value: T) -> Option<()> {
+        let cell = self.get_mut(index)?;
+        *cell = value;
+        return Some(());
+    }
+}
+
+// one row per line, cells separated by spaces
+impl<T: Clone + Default + fmt::Display> fmt::Display for Grid<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for i in 0..self.rows {
+            for j in 0..self.cols {
+                write!(f, "{} ", self.get(self.at(i, j)).unwrap())?;
+            }
+            writeln!(f)?;
+        }
+        return Ok(());
+    }
+}
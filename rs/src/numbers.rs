@@ -0,0 +1,52 @@
+// Number theory shared by the days that combine cycles, included with
+// `#[path = "../numbers.rs"] mod numbers;`
+#![allow(dead_code)]
+
+pub fn gcd(a: u128, b: u128) -> u128 {
+    if b == 0 {
+        return a;
+    }
+    return gcd(b, a % b);
+}
+
+pub fn lcm(a: u128, b: u128) -> u128 {
+    return a / gcd(a, b) * b;
+}
+
+pub fn extended_gcd(a: i128, b: i128) -> (i128, i128, i128) {
+    if b == 0 {
+        return (a, 1, 0);
+    }
+    let (g, x, y) = extended_gcd(b, a % b);
+    return (g, y, x - (a / b) * y);
+}
+
+// a * b % modulus without overflowing, for a and b below a modulus of at most 2^127
+pub fn mul_mod(mut a: u128, mut b: u128, modulus: u128) -> u128 {
+    let mut result = 0;
+    while b > 0 {
+        if b & 1 == 1 {
+            result = (result + a) % modulus;
+        }
+        a = (a + a) % modulus;
+        b >>= 1;
+    }
+    return result;
+}
+
+// x = a (mod m) and x = b (mod n) combine into x = c (mod lcm(m, n)) when they agree,
+// and when lcm(m, n) fits in an i128
+pub fn combine_congruences((a, m): (i128, i128), (b, n): (i128, i128)) -> Option<(i128, i128)> {
+    let (a, b) = (a.rem_euclid(m), b.rem_euclid(n));
+    let (g, p, _) = extended_gcd(m, n);
+    if (b - a) % g != 0 {
+        return None;
+    }
+    let l = (m / g).checked_mul(n)?;
+
+    // k = (b - a) / g * p (mod n / g), so that a + m k is b (mod n)
+    let step = n / g;
+    let k = mul_mod(((b - a) / g).rem_euclid(step) as u128, p.rem_euclid(step) as u128, step as u128) as i128;
+    // m k < l and a < m, so neither overflows
+    return Some(((a + m * k).rem_euclid(l), l));
+}